@@ -4,6 +4,10 @@ fn main() {
     compile_blueprint(&manifest_dir, "navbar");
     compile_blueprint(&manifest_dir, "side_panel");
     compile_blueprint(&manifest_dir, "content_panel");
+    compile_blueprint(&manifest_dir, "preferences");
+    compile_blueprint(&manifest_dir, "palette");
+    compile_blueprint(&manifest_dir, "preview_pane");
+    compile_blueprint(&manifest_dir, "quick_look");
 }
 
 fn compile_blueprint(manifest_dir: &str, name: &str) {
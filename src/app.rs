@@ -1,14 +1,29 @@
+use crate::settings::Settings;
+use crate::shortcuts;
 use crate::win::OwlWindow;
 use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk4 as gtk;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+// How long visits are collected before the settings file is written
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
 mod imp {
     use super::*;
 
     #[derive(Default)]
-    pub struct OwlApplication;
+    pub struct OwlApplication {
+        // Shared by every window, loaded on first activation
+        pub settings: OnceCell<Rc<RefCell<Settings>>>,
+        // Changes waiting for `save_settings_later` to write them
+        pub save_pending: Cell<bool>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for OwlApplication {
@@ -21,15 +36,21 @@ mod imp {
 
     impl ApplicationImpl for OwlApplication {
         fn activate(&self) {
-            let settings = gtk::Settings::default().unwrap();
-            settings.set_gtk_theme_name(Some("Adwaita"));
-
             let app = self.obj();
             let app_ref = app.downcast_ref::<super::OwlApplication>().unwrap();
+            // Load persisted settings before the first window reads them
+            app_ref.apply_theme();
             let window = OwlWindow::new(app_ref);
+            // The bindings are checked against the actions the window registers
+            app_ref.setup_accels();
 
             window.present();
         }
+
+        fn shutdown(&self) {
+            self.obj().flush_settings();
+            self.parent_shutdown();
+        }
     }
 
     impl GtkApplicationImpl for OwlApplication {}
@@ -47,4 +68,96 @@ impl OwlApplication {
             .property("application-id", "com.owl.app")
             .build()
     }
+
+    // Push changed preferences to the theme and every open window
+    pub fn apply_settings(&self) {
+        self.apply_theme();
+        self.setup_accels();
+        for window in self.windows() {
+            if let Ok(win) = window.downcast::<OwlWindow>() {
+                win.apply_settings();
+            }
+        }
+    }
+
+    // Refresh the sidebar of every window after saved searches changed
+    pub fn update_saved_searches(&self) {
+        let settings = self.settings();
+        let settings = settings.borrow();
+        for window in self.windows() {
+            if let Ok(win) = window.downcast::<OwlWindow>() {
+                win.side_panel()
+                    .set_saved_searches(&settings.saved_searches);
+            }
+        }
+    }
+
+    // Remember a visited folder and list it in every window's Go menu
+    pub fn record_location(&self, path: &Path) {
+        let settings = self.settings();
+        let mut settings = settings.borrow_mut();
+        settings.record_location(path);
+        for window in self.windows() {
+            if let Ok(win) = window.downcast::<OwlWindow>() {
+                win.update_recent_menu(&settings.recent_locations);
+            }
+        }
+        drop(settings);
+        self.save_settings_later();
+    }
+
+    // Writes the settings file a moment after the first unsaved change, so
+    // a burst of changes such as fast navigation costs one write
+    pub fn save_settings_later(&self) {
+        if self.imp().save_pending.replace(true) {
+            return;
+        }
+        glib::timeout_add_local_once(
+            SAVE_DELAY,
+            glib::clone!(
+                #[weak(rename_to = app)]
+                self,
+                move || app.flush_settings()
+            ),
+        );
+    }
+
+    fn flush_settings(&self) {
+        if self.imp().save_pending.replace(false) {
+            self.settings().borrow().save();
+        }
+    }
+
+    fn setup_accels(&self) {
+        // Every window has the same actions, so any of them will do
+        let Some(window) = self
+            .windows()
+            .into_iter()
+            .find_map(|w| w.downcast::<gtk::ApplicationWindow>().ok())
+        else {
+            return;
+        };
+        shortcuts::apply(self.upcast_ref(), &window, &self.settings().borrow());
+    }
+
+    fn apply_theme(&self) {
+        let Some(gtk_settings) = gtk::Settings::default() else {
+            return;
+        };
+        let settings = self.settings();
+        let settings = settings.borrow();
+        if settings.theme.is_empty() {
+            gtk_settings.reset_property("gtk-theme-name");
+        } else {
+            gtk_settings.set_gtk_theme_name(Some(&settings.theme));
+        }
+        gtk_settings.set_gtk_application_prefer_dark_theme(settings.prefer_dark);
+    }
+
+    pub fn settings(&self) -> Rc<RefCell<Settings>> {
+        self.imp()
+            .settings
+            .get_or_init(|| Rc::new(RefCell::new(Settings::load())))
+            .clone()
+    }
 }
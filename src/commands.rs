@@ -0,0 +1,173 @@
+use crate::palette::PaletteItem;
+use crate::settings::Settings;
+use crate::shortcuts;
+use crate::types::{Column, ViewMode};
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk4 as gtk;
+
+// Titles for actions without a default shortcut; the others take theirs from
+// shortcuts.rs
+const TITLES: &[(&str, &str)] = &[
+    ("win.go-root", "Go to the file system root"),
+    ("win.go-desktop", "Go to the desktop"),
+    ("win.go-templates", "Go to templates"),
+    ("win.save-search", "Save search"),
+    ("win.search-case-sensitive", "Case-sensitive search"),
+    ("win.close-other-tabs", "Close other tabs"),
+    ("win.close-window", "Close window"),
+    ("win.sort-natural", "Sort numbers by value"),
+    ("win.sort-locale", "Sort by the language's rules"),
+    (
+        "win.sort-ignore-punctuation",
+        "Ignore leading punctuation when sorting",
+    ),
+    ("win.folders-first", "Folders first"),
+    ("win.quick-look", "Quick look at the selection"),
+];
+
+// Actions that need input the palette cannot offer choices for
+const HIDDEN: &[&str] = &[
+    "win.command-palette",
+    "win.navigate",
+    "win.open-at-line",
+    "win.move-into",
+    "win.go-back-steps",
+    "win.go-forward-steps",
+    "win.sort-column",
+];
+
+// Every enabled action of `window` and the application, with one entry per
+// choice of the parameterized ones
+pub fn items(
+    app: &gtk::Application,
+    window: &gtk::ApplicationWindow,
+    settings: &Settings,
+) -> Vec<PaletteItem> {
+    let groups: [(&str, &gio::ActionGroup); 2] =
+        [("win", window.upcast_ref()), ("app", app.upcast_ref())];
+    let mut items = Vec::new();
+
+    for (prefix, group) in groups {
+        let mut names: Vec<String> = group.list_actions().iter().map(|n| n.to_string()).collect();
+        names.sort();
+        for name in names {
+            let action = format!("{}.{}", prefix, name);
+            if HIDDEN.contains(&action.as_str()) || !group.is_action_enabled(&name) {
+                continue;
+            }
+            let state = group.action_state(&name);
+
+            if group.action_parameter_type(&name).is_none() {
+                let mut subtitle = action.clone();
+                if let Some(on) = state.as_ref().and_then(|s| s.get::<bool>()) {
+                    subtitle.push_str(if on { " · on" } else { " · off" });
+                }
+                items.push(PaletteItem {
+                    title: title(&action),
+                    subtitle,
+                    accel: first_accel(app, &action),
+                    action,
+                    target: None,
+                });
+                continue;
+            }
+
+            for (target, title) in choices(&action, settings) {
+                let detailed = gio::Action::print_detailed_name(&action, Some(&target));
+                let mut subtitle = detailed.to_string();
+                if state.as_ref() == Some(&target) {
+                    subtitle.push_str(" · current");
+                }
+                items.push(PaletteItem {
+                    title,
+                    subtitle,
+                    accel: first_accel(app, &detailed),
+                    action: action.clone(),
+                    target: Some(target),
+                });
+            }
+        }
+    }
+    items
+}
+
+// Targets of a parameterized action with their titles
+fn choices(action: &str, settings: &Settings) -> Vec<(glib::Variant, String)> {
+    match action {
+        "win.sort" => Column::ALL
+            .iter()
+            .map(|c| (c.id().to_variant(), format!("Sort by {}", c.label())))
+            .collect(),
+        "win.order" => vec![
+            ("ascending".to_variant(), "Sort ascending".to_string()),
+            ("descending".to_variant(), "Sort descending".to_string()),
+        ],
+        "win.view" => [
+            (ViewMode::List, "List view"),
+            (ViewMode::Grid, "Grid view"),
+            (ViewMode::Compact, "Compact view"),
+            (ViewMode::Columns, "Columns view"),
+        ]
+        .into_iter()
+        .map(|(mode, title)| (format!("'{}'", mode.id()).to_variant(), title.to_string()))
+        .collect(),
+        "win.search-mode" => [
+            ("substring", "Search names containing the text"),
+            ("glob", "Search names with a glob pattern"),
+            ("regex", "Search names with a regular expression"),
+            ("contents", "Search inside files"),
+        ]
+        .into_iter()
+        .map(|(id, title)| (id.to_variant(), title.to_string()))
+        .collect(),
+        "win.open-saved-search" => settings
+            .saved_searches
+            .iter()
+            .map(|s| {
+                (
+                    s.name.to_variant(),
+                    format!("Open saved search “{}”", s.name),
+                )
+            })
+            .collect(),
+        "win.remove-saved-search" => settings
+            .saved_searches
+            .iter()
+            .map(|s| {
+                (
+                    s.name.to_variant(),
+                    format!("Remove saved search “{}”", s.name),
+                )
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// From shortcuts.rs or `TITLES`, else made from the action name
+fn title(action: &str) -> String {
+    let known = shortcuts::GROUPS
+        .iter()
+        .flat_map(|(_, shortcuts)| shortcuts.iter())
+        .map(|s| (s.action, s.title))
+        .chain(TITLES.iter().copied())
+        .find(|(a, _)| *a == action);
+    if let Some((_, title)) = known {
+        return title.to_string();
+    }
+    let name = action.split_once('.').map(|(_, n)| n).unwrap_or(action);
+    let mut title = name.replace('-', " ");
+    if let Some(first) = title.get(..1) {
+        title = first.to_uppercase() + &title[1..];
+    }
+    title
+}
+
+fn first_accel(app: &gtk::Application, detailed: &str) -> String {
+    app.accels_for_action(detailed)
+        .first()
+        .map(|a| a.to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,55 @@
+use crate::launcher;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk4 as gtk;
+use std::path::Path;
+
+// Editors we know how to open at a given line, in order of preference.
+// `%f` is replaced by the file and `%l` by the line number.
+const KNOWN_EDITORS: &[(&str, &str)] = &[
+    ("gnome-text-editor", "gnome-text-editor +%l %f"),
+    ("gedit", "gedit +%l %f"),
+    ("kate", "kate --line %l %f"),
+    ("xed", "xed +%l %f"),
+    ("pluma", "pluma +%l %f"),
+    ("code", "code --goto %f:%l"),
+    ("codium", "codium --goto %f:%l"),
+    ("subl", "subl %f:%l"),
+    ("zed", "zed %f:%l"),
+];
+
+// Command template to use when the user has not configured one
+pub fn detect() -> Option<String> {
+    KNOWN_EDITORS
+        .iter()
+        .find(|(program, _)| glib::find_program_in_path(program).is_some())
+        .map(|(_, template)| template.to_string())
+}
+
+// Opens `file` at `line`; without a configured or known editor the file is
+// handed to its default application and the line is lost
+pub fn open_at_line(template: &str, file: &Path, line: u32) -> Result<(), String> {
+    let template = if template.trim().is_empty() {
+        match detect() {
+            Some(template) => template,
+            None => return open_default(file),
+        }
+    } else {
+        template.to_string()
+    };
+    let file_str = file.to_string_lossy();
+    let line_str = line.to_string();
+    launcher::spawn(
+        "editor",
+        &template,
+        &[("%f", &*file_str), ("%l", line_str.as_str())],
+        file.parent(),
+    )
+}
+
+fn open_default(file: &Path) -> Result<(), String> {
+    let uri = gio::File::for_path(file).uri();
+    gio::AppInfo::launch_default_for_uri(&uri, None::<&gio::AppLaunchContext>)
+        .map_err(|e| format!("Could not open \"{}\": {}", file.display(), e))
+}
@@ -1,39 +1,66 @@
+use crate::types::{Column, DisplayFormat, SizeUnits};
+use gtk::gio;
+use gtk::glib;
+use gtk4 as gtk;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
 use std::fs;
+use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 #[derive(Clone)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub name: String,
     pub size: u64,
     pub is_dir: bool,
-    pub modified: std::time::SystemTime,
+    pub modified: SystemTime,
+    pub created: Option<SystemTime>,
+    pub accessed: SystemTime,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub link_target: Option<PathBuf>,
+    // Counted on first use; reading every folder would slow down listings
+    item_count: OnceCell<Option<usize>>,
 }
 
 impl FileEntry {
     pub fn from_path(path: PathBuf) -> Option<Self> {
         let metadata = fs::metadata(&path).ok()?;
         let name = path.file_name()?.to_string_lossy().to_string();
+        let is_dir = path.is_dir();
 
+        // Symlinks report the link itself for permissions, the target for everything else
+        let link_meta = fs::symlink_metadata(&path).ok()?;
+        let link_target = if link_meta.file_type().is_symlink() {
+            fs::read_link(&path).ok()
+        } else {
+            None
+        };
         Some(Self {
-            is_dir: path.is_dir(),
+            is_dir,
             size: metadata.len(),
             modified: metadata.modified().ok()?,
+            created: metadata.created().ok(),
+            accessed: UNIX_EPOCH + Duration::from_secs(metadata.atime().max(0) as u64),
+            mode: link_meta.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            link_target,
+            item_count: OnceCell::new(),
             path,
             name,
         })
     }
 
-    pub fn size_display(&self) -> String {
+    pub fn size_display(&self, units: SizeUnits) -> String {
         if self.is_dir {
             return "—".to_string();
         }
-        match self.size {
-            0 => "0 B".to_string(),
-            1..=1023 => format!("{} B", self.size),
-            1024..=1048575 => format!("{:.1} KB", self.size as f64 / 1024.0),
-            1048576..=1073741823 => format!("{:.1} MB", self.size as f64 / 1_048_576.0),
-            _ => format!("{:.1} GB", self.size as f64 / 1_073_741_824.0),
-        }
+        format_size(self.size, units)
     }
 
     pub fn kind_display(&self) -> String {
@@ -55,15 +82,116 @@ impl FileEntry {
         }
     }
 
-    pub fn date_display(&self) -> String {
-        use std::time::UNIX_EPOCH;
-        let sec = self
-            .modified
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        let (y, mo, d, h, mi) = secs_to_date(sec);
-        format!("{:04}-{:02}-{:02} {:02}:{:02}", y, mo, d, h, mi)
+    pub fn date_display(&self, format: &str) -> String {
+        format_time(self.modified, format)
+    }
+
+    pub fn permissions_display(&self) -> String {
+        let kind = if self.link_target.is_some() {
+            'l'
+        } else if self.is_dir {
+            'd'
+        } else {
+            '-'
+        };
+        let mut out = String::with_capacity(10);
+        out.push(kind);
+        for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')]
+        {
+            let bits = (self.mode >> shift) & 0o7;
+            out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            out.push(match (bits & 0o1 != 0, self.mode & special != 0) {
+                (true, true) => special_char,
+                (false, true) => special_char.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
+            });
+        }
+        out
+    }
+
+    pub fn owner_display(&self) -> String {
+        static USERS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+        cached_name(&USERS, self.uid, user_name)
+    }
+
+    pub fn group_display(&self) -> String {
+        static GROUPS: OnceLock<Mutex<HashMap<u32, String>>> = OnceLock::new();
+        cached_name(&GROUPS, self.gid, group_name)
+    }
+
+    pub fn mime_type(&self) -> String {
+        if self.is_dir {
+            return "inode/directory".to_string();
+        }
+        let (content_type, _) = gio::content_type_guess(Some(&self.path), None::<&[u8]>);
+        gio::content_type_get_mime_type(&content_type)
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| content_type.to_string())
+    }
+
+    pub fn extension(&self) -> String {
+        if self.is_dir {
+            return String::new();
+        }
+        self.path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+
+    // Children of a folder, None for files and unreadable folders
+    pub fn item_count(&self) -> Option<usize> {
+        *self.item_count.get_or_init(|| {
+            if self.is_dir {
+                fs::read_dir(&self.path).ok().map(|d| d.count())
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn item_count_display(&self) -> String {
+        match self.item_count() {
+            Some(1) => "1 item".to_string(),
+            Some(n) => format!("{} items", n),
+            None => "—".to_string(),
+        }
+    }
+
+    pub fn location_display(&self) -> String {
+        self.path
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    // Text shown in the detailed list view for a given column
+    pub fn column_display(&self, column: Column, format: &DisplayFormat) -> String {
+        match column {
+            Column::Name => self.name.clone(),
+            Column::Size => self.size_display(format.size_units),
+            Column::Type => self.kind_display(),
+            Column::Date => self.date_display(&format.date_format),
+            Column::Permissions => self.permissions_display(),
+            Column::Owner => self.owner_display(),
+            Column::Group => self.group_display(),
+            Column::Created => self
+                .created
+                .map(|t| format_time(t, &format.date_format))
+                .unwrap_or_else(|| "—".to_string()),
+            Column::Accessed => format_time(self.accessed, &format.date_format),
+            Column::MimeType => self.mime_type(),
+            Column::LinkTarget => self
+                .link_target
+                .as_ref()
+                .map(|t| t.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            Column::ItemCount => self.item_count_display(),
+            Column::Extension => self.extension(),
+            Column::Location => self.location_display(),
+        }
     }
 
     pub fn icon_name(&self) -> &'static str {
@@ -95,6 +223,107 @@ impl FileEntry {
     }
 }
 
+pub fn format_size(size: u64, units: SizeUnits) -> String {
+    let (base, suffixes) = match units {
+        SizeUnits::Binary => (1024.0, ["KB", "MB", "GB", "TB"]),
+        SizeUnits::Decimal => (1000.0, ["kB", "MB", "GB", "TB"]),
+    };
+    if (size as f64) < base {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / base;
+    let mut suffix = suffixes[0];
+    for next in &suffixes[1..] {
+        if value < base {
+            break;
+        }
+        value /= base;
+        suffix = next;
+    }
+    format!("{:.1} {}", value, suffix)
+}
+
+// Local time in the user's format, falling back to UTC ISO if the format is invalid
+fn format_time(time: SystemTime, format: &str) -> String {
+    let sec = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if let Ok(text) = glib::DateTime::from_unix_local(sec as i64).and_then(|dt| dt.format(format)) {
+        return text.to_string();
+    }
+    let (y, mo, d, h, mi) = secs_to_date(sec);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", y, mo, d, h, mi)
+}
+
+// Name of a uid or gid, looked up once per id and shared by every thread;
+// ids without a name show as the number
+fn cached_name(
+    cache: &OnceLock<Mutex<HashMap<u32, String>>>,
+    id: u32,
+    lookup: fn(u32) -> Option<String>,
+) -> String {
+    let mut cache = cache
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(id)
+        .or_insert_with(|| lookup(id).unwrap_or_else(|| id.to_string()))
+        .clone()
+}
+
+// Larger buffers than this are not worth retrying with
+const MAX_NSS_BUFFER: usize = 1 << 20;
+
+// Asks the system's user database through NSS, so users from LDAP,
+// systemd-homed and the like resolve as well as those in /etc/passwd
+fn user_name(uid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        // SAFETY: passwd is plain data for which all zeroes is valid
+        let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: every pointer refers to live memory of the size given
+        let err =
+            unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if err == libc::ERANGE && buf.len() < MAX_NSS_BUFFER {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if err != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success pw_name points to a terminated string in `buf`
+        let name = unsafe { CStr::from_ptr(pwd.pw_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
+// The group database counterpart of `user_name`
+fn group_name(gid: u32) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        // SAFETY: group is plain data for which all zeroes is valid
+        let mut grp: libc::group = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+        // SAFETY: every pointer refers to live memory of the size given
+        let err =
+            unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if err == libc::ERANGE && buf.len() < MAX_NSS_BUFFER {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if err != 0 || result.is_null() {
+            return None;
+        }
+        // SAFETY: on success gr_name points to a terminated string in `buf`
+        let name = unsafe { CStr::from_ptr(grp.gr_name) };
+        return Some(name.to_string_lossy().into_owned());
+    }
+}
+
 fn secs_to_date(secs: u64) -> (u64, u64, u64, u64, u64) {
     let min = secs / 60;
     let hour = min / 60;
@@ -0,0 +1,96 @@
+use gtk::gio;
+use gtk::prelude::*;
+use gtk4 as gtk;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Copies `source` into the folder `dest_dir`, folders recursively. Existing
+// files are never overwritten.
+pub fn copy_into(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    let target = target_path(source, dest_dir)?;
+    copy_recursive(source, &target)
+        .map_err(|e| format!("Could not copy \"{}\": {}", source.display(), e))?;
+    Ok(target)
+}
+
+// Moves `source` into the folder `dest_dir`
+pub fn move_into(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    let target = target_path(source, dest_dir)?;
+    if fs::rename(source, &target).is_ok() {
+        return Ok(target);
+    }
+    // Renaming fails across file systems; copy, then remove the original
+    copy_recursive(source, &target)
+        .and_then(|_| remove(source))
+        .map_err(|e| format!("Could not move \"{}\": {}", source.display(), e))?;
+    Ok(target)
+}
+
+// Gives `path` the name `name` in the same folder
+pub fn rename(path: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.contains('/') || name == "." || name == ".." {
+        return Err(format!("\"{}\" is not a valid name", name));
+    }
+    let Some(parent) = path.parent() else {
+        return Err(format!("Cannot rename \"{}\"", path.display()));
+    };
+    let target = parent.join(name);
+    if target.symlink_metadata().is_ok() {
+        return Err(format!("\"{}\" already exists", target.display()));
+    }
+    fs::rename(path, &target)
+        .map_err(|e| format!("Could not rename \"{}\": {}", path.display(), e))?;
+    Ok(target)
+}
+
+// Moves `path` to the desktop trash
+pub fn trash(path: &Path) -> Result<(), String> {
+    gio::File::for_path(path)
+        .trash(None::<&gio::Cancellable>)
+        .map_err(|e| format!("Could not move \"{}\" to the trash: {}", path.display(), e))
+}
+
+// Removes `path` for good, folders with their contents
+pub fn delete(path: &Path) -> Result<(), String> {
+    remove(path).map_err(|e| format!("Could not delete \"{}\": {}", path.display(), e))
+}
+
+fn target_path(source: &Path, dest_dir: &Path) -> Result<PathBuf, String> {
+    let Some(name) = source.file_name() else {
+        return Err(format!("Cannot copy \"{}\"", source.display()));
+    };
+    if dest_dir.starts_with(source) {
+        return Err(format!("Cannot copy \"{}\" into itself", source.display()));
+    }
+    let target = dest_dir.join(name);
+    if target.symlink_metadata().is_ok() {
+        return Err(format!("\"{}\" already exists", target.display()));
+    }
+    Ok(target)
+}
+
+// Symlinks are copied as links, not followed
+fn copy_recursive(source: &Path, target: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(source)?;
+    if meta.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(source)?, target)
+    } else if meta.is_dir() {
+        fs::create_dir(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        fs::set_permissions(target, meta.permissions())
+    } else {
+        fs::copy(source, target).map(|_| ())
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
@@ -0,0 +1,279 @@
+// Minimal syntax highlighting for the preview pane: keywords, strings,
+// comments and numbers of common languages, found by the file extension
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Token {
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+impl Token {
+    // Name of the text tag that styles it
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Token::Keyword => "keyword",
+            Token::String => "string",
+            Token::Comment => "comment",
+            Token::Number => "number",
+        }
+    }
+}
+
+struct Syntax {
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    // Otherwise a quote only starts a character literal such as 'a' or '\n'
+    single_quote_strings: bool,
+}
+
+const C_LIKE_COMMENTS: Option<(&str, &str)> = Some(("/*", "*/"));
+
+fn syntax(extension: &str) -> Option<Syntax> {
+    let syntax = match extension {
+        "rs" => Syntax {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+                "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+            ],
+            line_comments: &["//"],
+            block_comment: C_LIKE_COMMENTS,
+            single_quote_strings: false,
+        },
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "java" | "kt" | "cs" | "go" | "swift" => {
+            Syntax {
+                keywords: &[
+                    "auto",
+                    "break",
+                    "case",
+                    "catch",
+                    "char",
+                    "class",
+                    "const",
+                    "continue",
+                    "default",
+                    "defer",
+                    "delete",
+                    "do",
+                    "double",
+                    "else",
+                    "enum",
+                    "extends",
+                    "extern",
+                    "false",
+                    "final",
+                    "float",
+                    "for",
+                    "fun",
+                    "func",
+                    "go",
+                    "if",
+                    "implements",
+                    "import",
+                    "int",
+                    "interface",
+                    "long",
+                    "namespace",
+                    "new",
+                    "null",
+                    "nullptr",
+                    "package",
+                    "private",
+                    "protected",
+                    "public",
+                    "return",
+                    "short",
+                    "signed",
+                    "sizeof",
+                    "static",
+                    "struct",
+                    "switch",
+                    "template",
+                    "this",
+                    "throw",
+                    "true",
+                    "try",
+                    "type",
+                    "typedef",
+                    "union",
+                    "unsigned",
+                    "using",
+                    "val",
+                    "var",
+                    "virtual",
+                    "void",
+                    "volatile",
+                    "while",
+                ],
+                line_comments: &["//"],
+                block_comment: C_LIKE_COMMENTS,
+                single_quote_strings: false,
+            }
+        }
+        "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "css" | "scss" | "json" => Syntax {
+            keywords: &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "for",
+                "from",
+                "function",
+                "if",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "null",
+                "of",
+                "return",
+                "static",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "type",
+                "typeof",
+                "undefined",
+                "var",
+                "void",
+                "while",
+                "yield",
+            ],
+            line_comments: &["//"],
+            block_comment: C_LIKE_COMMENTS,
+            single_quote_strings: true,
+        },
+        "py" => Syntax {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "False", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise",
+                "return", "True", "try", "while", "with", "yield",
+            ],
+            line_comments: &["#"],
+            block_comment: None,
+            single_quote_strings: true,
+        },
+        "sh" | "bash" | "zsh" | "fish" => Syntax {
+            keywords: &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function",
+                "if", "in", "local", "return", "then", "until", "while",
+            ],
+            line_comments: &["#"],
+            block_comment: None,
+            single_quote_strings: true,
+        },
+        "toml" | "yaml" | "yml" | "ini" | "conf" | "cfg" => Syntax {
+            keywords: &["true", "false", "yes", "no", "null"],
+            line_comments: &["#", ";"],
+            block_comment: None,
+            single_quote_strings: true,
+        },
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+// Highlighted ranges of `text` in character offsets, ready for a text
+// buffer; empty for unknown languages
+pub fn highlight(text: &str, extension: &str) -> Vec<(usize, usize, Token)> {
+    let Some(syntax) = syntax(&extension.to_lowercase()) else {
+        return Vec::new();
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let starts_with = |i: usize, pattern: &str| {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(k, p)| chars.get(i + k) == Some(&p))
+    };
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        if let Some((open, close)) = syntax
+            .block_comment
+            .filter(|(open, _)| starts_with(i, open))
+        {
+            let mut end = i + open.chars().count();
+            while end < chars.len() && !starts_with(end, close) {
+                end += 1;
+            }
+            end = (end + close.chars().count()).min(chars.len());
+            tokens.push((i, end, Token::Comment));
+            i = end;
+            continue;
+        }
+
+        if syntax.line_comments.iter().any(|p| starts_with(i, p)) {
+            let end = (i..chars.len())
+                .find(|&k| chars[k] == '\n')
+                .unwrap_or(chars.len());
+            tokens.push((i, end, Token::Comment));
+            i = end;
+            continue;
+        }
+
+        let string_quote = c == '"' || (c == '\'' && syntax.single_quote_strings);
+        let char_literal = c == '\''
+            && !syntax.single_quote_strings
+            && (chars.get(i + 2) == Some(&'\'') || chars.get(i + 1) == Some(&'\\'));
+        if string_quote || char_literal {
+            // Strings end at the closing quote or, unterminated, at the line end
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != c && chars[end] != '\n' {
+                if chars[end] == '\\' {
+                    end += 1;
+                }
+                end += 1;
+            }
+            end = (end + 1).min(chars.len());
+            tokens.push((i, end, Token::String));
+            i = end;
+            continue;
+        }
+
+        if is_ident(c) {
+            let mut end = i;
+            while end < chars.len()
+                && (is_ident(chars[end]) || (c.is_ascii_digit() && chars[end] == '.'))
+            {
+                end += 1;
+            }
+            let word: String = chars[i..end].iter().collect();
+            if c.is_ascii_digit() {
+                tokens.push((i, end, Token::Number));
+            } else if syntax.keywords.contains(&word.as_str()) {
+                tokens.push((i, end, Token::Keyword));
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    tokens
+}
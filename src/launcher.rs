@@ -0,0 +1,40 @@
+use gtk::glib;
+use gtk4 as gtk;
+use std::path::Path;
+use std::process::Command;
+
+// Starts a command line configured in the settings. `template` is split like
+// a shell would; every `(placeholder, value)` pair is then replaced in each
+// argument. `what` names the program in error messages, such as "terminal".
+pub fn spawn(
+    what: &str,
+    template: &str,
+    substitutions: &[(&str, &str)],
+    dir: Option<&Path>,
+) -> Result<(), String> {
+    let argv = glib::shell_parse_argv(template)
+        .map_err(|e| format!("Invalid {} command \"{}\": {}", what, template, e))?;
+    let mut args = argv.iter().map(|arg| {
+        substitutions.iter().fold(
+            arg.to_string_lossy().to_string(),
+            |arg, (placeholder, value)| arg.replace(placeholder, value),
+        )
+    });
+    let Some(program) = args.next() else {
+        return Err(format!("The {} command is empty", what));
+    };
+
+    let mut command = Command::new(&program);
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Could not start \"{}\": {}", program, e))?;
+    // Reaped in the background so a closed program does not stay a zombie
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
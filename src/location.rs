@@ -0,0 +1,214 @@
+use crate::file_entry::FileEntry;
+use gtk::gio;
+use gtk::prelude::*;
+use gtk4 as gtk;
+use std::path::{Component, Path, PathBuf};
+
+// Turns what was typed in the path entry into an absolute path: `file://`
+// URIs, `~`, `$VAR` and `${VAR}` are expanded and relative paths are taken
+// from `current`
+pub fn resolve(text: &str, current: &Path) -> Option<PathBuf> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if text.starts_with("file:") {
+        return gio::File::for_uri(text).path().map(|p| normalize(&p));
+    }
+
+    let expanded = expand_vars(text)?;
+    let expanded = match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", std::env::var("HOME").ok()?, rest)
+        }
+        _ => expanded,
+    };
+    Some(normalize(&current.join(expanded)))
+}
+
+// Unknown variables make the whole path invalid rather than silently empty
+fn expand_vars(text: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if braced && c == '}' {
+                chars.next();
+                break;
+            }
+            if !braced && !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() {
+            result.push('$');
+            continue;
+        }
+        result.push_str(&std::env::var(&name).ok()?);
+    }
+    Some(result)
+}
+
+// Removes `.` and `..` without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+// Folders that can complete `text`, as full entry texts ending in `/`.
+// Hidden folders are only offered once a `.` has been typed.
+pub fn completions(text: &str, current: &Path) -> Vec<String> {
+    let (dir_text, prefix) = match text.rfind('/') {
+        Some(i) => (&text[..=i], &text[i + 1..]),
+        None => ("", text),
+    };
+    let dir = if dir_text.is_empty() {
+        Some(current.to_path_buf())
+    } else {
+        resolve(dir_text, current)
+    };
+    let Some(dir) = dir else {
+        return vec![];
+    };
+
+    let prefix_lower = prefix.to_lowercase();
+    let mut names: Vec<String> = FileEntry::list_directory(&dir)
+        .into_iter()
+        .filter(|e| e.is_dir)
+        .filter(|e| prefix.starts_with('.') || !e.name.starts_with('.'))
+        .filter(|e| e.name.to_lowercase().starts_with(&prefix_lower))
+        .map(|e| e.name)
+        .collect();
+    names.sort_by_key(|n| n.to_lowercase());
+    names
+        .into_iter()
+        .map(|name| format!("{}{}/", dir_text, name))
+        .collect()
+}
+
+// Longest start shared by all `candidates`, compared without case so typing
+// "doc" still completes to "Documents/"
+pub fn common_prefix(candidates: &[String]) -> Option<String> {
+    let first = candidates.first()?;
+    let mut len = first.len();
+    for other in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(other.chars())
+            .take_while(|((_, a), b)| a.to_lowercase().eq(b.to_lowercase()))
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    Some(first[..len].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn resolved(text: &str) -> Option<String> {
+        resolve(text, Path::new("/home/user/src")).map(|p| p.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn resolves_relative_and_absolute_paths() {
+        assert_eq!(resolved("/etc/"), Some("/etc".to_string()));
+        assert_eq!(resolved("owl"), Some("/home/user/src/owl".to_string()));
+        assert_eq!(
+            resolved("  ../docs/./a "),
+            Some("/home/user/docs/a".to_string())
+        );
+        assert_eq!(resolved("/../.."), Some("/".to_string()));
+        assert_eq!(resolved("   "), None);
+    }
+
+    #[test]
+    fn expands_home_and_variables() {
+        let home = std::env::var("HOME").unwrap();
+        let home = normalize(Path::new(&home)).to_string_lossy().to_string();
+        assert_eq!(resolved("~"), Some(home.clone()));
+        assert_eq!(resolved("~/Music"), Some(format!("{}/Music", home)));
+        assert_eq!(resolved("$HOME/Music"), Some(format!("{}/Music", home)));
+        assert_eq!(resolved("${HOME}/Music"), Some(format!("{}/Music", home)));
+        // Only a leading `~` alone or before a slash is the home folder
+        assert_eq!(
+            resolved("~other"),
+            Some("/home/user/src/~other".to_string())
+        );
+        assert_eq!(
+            resolved("price$"),
+            Some("/home/user/src/price$".to_string())
+        );
+        assert_eq!(resolved("$OWL_TEST_UNSET_VARIABLE/x"), None);
+    }
+
+    #[test]
+    fn resolves_file_uris() {
+        assert_eq!(
+            resolved("file:///tmp/a%20b/../c"),
+            Some("/tmp/c".to_string())
+        );
+    }
+
+    #[test]
+    fn common_prefix_ignores_case() {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&[]), None);
+        assert_eq!(common_prefix(&strings(&["src/"])), Some("src/".to_string()));
+        assert_eq!(
+            common_prefix(&strings(&["Documents/", "Downloads/"])),
+            Some("Do".to_string())
+        );
+        assert_eq!(
+            common_prefix(&strings(&["Documents/", "documents-old/"])),
+            Some("Documents".to_string())
+        );
+        assert_eq!(
+            common_prefix(&strings(&["Été/", "été 2024/"])),
+            Some("Été".to_string())
+        );
+        assert_eq!(common_prefix(&strings(&["a/", "b/"])), Some(String::new()));
+    }
+
+    #[test]
+    fn completes_visible_folders() {
+        let dir = std::env::temp_dir().join(format!("owl-location-{}", std::process::id()));
+        for name in ["Docs", "downloads", ".dotfiles", "Music"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("do-not-list.txt"), "").unwrap();
+
+        assert_eq!(completions("do", &dir), ["Docs/", "downloads/"]);
+        assert_eq!(completions(".d", &dir), [".dotfiles/"]);
+        let text = format!("{}/m", dir.display());
+        assert_eq!(
+            completions(&text, Path::new("/")),
+            [format!("{}/Music/", dir.display())]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,21 @@
 mod app;
+mod commands;
+mod editor;
 mod file_entry;
+mod file_ops;
+mod highlight;
+mod launcher;
+mod location;
+mod palette;
+mod preferences;
+mod preview;
+mod quick_look;
+mod search;
+mod search_filters;
+mod settings;
+mod shortcuts;
+mod terminal;
+mod thumbnails;
 mod types;
 mod widgets;
 mod win;
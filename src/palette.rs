@@ -0,0 +1,255 @@
+use gtk::gdk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk4 as gtk;
+
+// Most rows shown at once; typing narrows the rest down
+const MAX_ROWS: usize = 200;
+
+// One choice; activating it runs `action` on the window that opened the palette
+pub struct PaletteItem {
+    pub title: String,
+    pub subtitle: String,
+    // Accelerator in `gtk::accelerator_parse` syntax, empty for none
+    pub accel: String,
+    pub action: String,
+    pub target: Option<glib::Variant>,
+}
+
+mod imp {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(file = "../data/palette.ui")]
+    pub struct OwlPalette {
+        #[template_child]
+        pub entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub list: TemplateChild<gtk::ListBox>,
+        // In ranking order when nothing is typed
+        pub items: RefCell<Vec<PaletteItem>>,
+        // Indices into `items` of the rows, best match first
+        pub shown: RefCell<Vec<usize>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwlPalette {
+        const NAME: &'static str = "OwlPalette";
+        type Type = super::OwlPalette;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OwlPalette {}
+    impl WidgetImpl for OwlPalette {}
+    impl WindowImpl for OwlPalette {}
+}
+
+glib::wrapper! {
+    pub struct OwlPalette(ObjectSubclass<imp::OwlPalette>)
+        @extends gtk::Widget, gtk::Window,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget,
+                    gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl OwlPalette {
+    pub fn new(parent: &impl IsA<gtk::Window>, placeholder: &str, items: Vec<PaletteItem>) -> Self {
+        let palette: Self = glib::Object::builder()
+            .property("transient-for", parent)
+            .build();
+        let imp = palette.imp();
+        imp.entry.set_placeholder_text(Some(placeholder));
+        *imp.items.borrow_mut() = items;
+        palette.setup_signals();
+        palette.refresh();
+        palette
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+        imp.entry.connect_search_changed(glib::clone!(
+            #[weak(rename_to = palette)]
+            self,
+            move |_| palette.refresh()
+        ));
+        imp.entry.connect_activate(glib::clone!(
+            #[weak(rename_to = palette)]
+            self,
+            move |_| {
+                if let Some(row) = palette.imp().list.selected_row() {
+                    palette.run(row.index());
+                }
+            }
+        ));
+        imp.entry.connect_stop_search(glib::clone!(
+            #[weak(rename_to = palette)]
+            self,
+            move |_| palette.close()
+        ));
+        imp.list.connect_row_activated(glib::clone!(
+            #[weak(rename_to = palette)]
+            self,
+            move |_, row| palette.run(row.index())
+        ));
+
+        // Up and Down move through the rows while the focus stays in the entry
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = palette)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, _| {
+                let offset = match keyval {
+                    gdk::Key::Down => 1,
+                    gdk::Key::Up => -1,
+                    _ => return glib::Propagation::Proceed,
+                };
+                palette.move_selection(offset);
+                glib::Propagation::Stop
+            }
+        ));
+        imp.entry.add_controller(keys);
+    }
+
+    // Lists the items matching the query, best first; ties keep the given order
+    fn refresh(&self) {
+        let imp = self.imp();
+        let query = imp.entry.text().trim().to_lowercase();
+        let items = imp.items.borrow();
+
+        let mut scored: Vec<(i64, usize)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                if query.is_empty() {
+                    return Some((0, i));
+                }
+                // The title counts more than the subtitle
+                let title = fuzzy_score(&item.title, &query).map(|s| s + 20);
+                let subtitle = fuzzy_score(&item.subtitle, &query);
+                title.max(subtitle).map(|s| (s, i))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.truncate(MAX_ROWS);
+
+        let list = &imp.list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        for (_, i) in &scored {
+            list.append(&Self::make_row(&items[*i]));
+        }
+        *imp.shown.borrow_mut() = scored.into_iter().map(|(_, i)| i).collect();
+        list.select_row(list.row_at_index(0).as_ref());
+    }
+
+    fn make_row(item: &PaletteItem) -> gtk::ListBoxRow {
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        hbox.set_margin_top(4);
+        hbox.set_margin_bottom(4);
+        hbox.set_margin_start(8);
+        hbox.set_margin_end(8);
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        vbox.set_hexpand(true);
+        let title = gtk::Label::new(Some(&item.title));
+        title.set_xalign(0.0);
+        title.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        vbox.append(&title);
+        if !item.subtitle.is_empty() {
+            let subtitle = gtk::Label::new(Some(&item.subtitle));
+            subtitle.set_xalign(0.0);
+            subtitle.set_ellipsize(gtk::pango::EllipsizeMode::Start);
+            subtitle.add_css_class("dim-label");
+            subtitle.add_css_class("caption");
+            vbox.append(&subtitle);
+        }
+        hbox.append(&vbox);
+
+        if let Some((key, mods)) = gtk::accelerator_parse(item.accel.as_str()) {
+            let accel = gtk::Label::new(Some(&gtk::accelerator_get_label(key, mods)));
+            accel.add_css_class("dim-label");
+            hbox.append(&accel);
+        }
+
+        let row = gtk::ListBoxRow::new();
+        row.set_child(Some(&hbox));
+        row
+    }
+
+    fn move_selection(&self, offset: i32) {
+        let list = &self.imp().list;
+        let count = self.imp().shown.borrow().len() as i32;
+        if count == 0 {
+            return;
+        }
+        let index = list.selected_row().map(|r| r.index()).unwrap_or(-1) + offset;
+        if let Some(row) = list.row_at_index(index.clamp(0, count - 1)) {
+            list.select_row(Some(&row));
+            // Scroll to the row without taking the focus from the entry
+            if let Some(adjustment) = list.adjustment() {
+                let (_, y) = row
+                    .translate_coordinates(list, 0.0, 0.0)
+                    .unwrap_or_default();
+                adjustment.clamp_page(y, y + row.height() as f64);
+            }
+        }
+    }
+
+    // Closes the palette first so the action sees the window it came from
+    fn run(&self, index: i32) {
+        let imp = self.imp();
+        let Some(i) = imp.shown.borrow().get(index as usize).copied() else {
+            return;
+        };
+        let (action, target) = {
+            let items = imp.items.borrow();
+            (items[i].action.clone(), items[i].target.clone())
+        };
+        let parent = self.transient_for();
+        self.close();
+        if let Some(parent) = parent {
+            let _ = parent.activate_action(&action, target.as_ref());
+        }
+    }
+}
+
+// Higher is better; None unless the letters of `query` (lowercase) appear in
+// `text` in order. Whole substrings, word starts and runs of adjacent letters
+// score more, gaps less.
+fn fuzzy_score(text: &str, query: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars() {
+        let found = (position..text.len()).find(|&i| text[i] == q)?;
+        score += 1;
+        if found == 0 || matches!(text[found - 1], '/' | ' ' | '-' | '_' | '.') {
+            score += 8;
+        }
+        match previous {
+            Some(p) if p + 1 == found => score += 5,
+            Some(p) => score -= (found - p - 1).min(10) as i64,
+            None => {}
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    let haystack: String = text.iter().collect();
+    if haystack.contains(query) {
+        score += 50;
+    }
+    Some(score)
+}
@@ -0,0 +1,291 @@
+use crate::app::OwlApplication;
+use crate::settings::Settings;
+use crate::types::{SizeUnits, ViewMode};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk4 as gtk;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(file = "../data/preferences.ui")]
+    pub struct OwlPreferences {
+        #[template_child]
+        pub theme_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub prefer_dark_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub view_mode_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub show_hidden_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub show_thumbnails_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub date_format_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub size_units_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub single_click_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub type_ahead_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub confirm_delete_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub history_size_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub terminal_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub editor_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub max_file_size_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub respect_gitignore_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub remember_views_switch: TemplateChild<gtk::Switch>,
+        #[template_child]
+        pub forget_views_button: TemplateChild<gtk::Button>,
+
+        pub app: glib::WeakRef<OwlApplication>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwlPreferences {
+        const NAME: &'static str = "OwlPreferences";
+        type Type = super::OwlPreferences;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OwlPreferences {}
+    impl WidgetImpl for OwlPreferences {}
+    impl WindowImpl for OwlPreferences {}
+}
+
+glib::wrapper! {
+    pub struct OwlPreferences(ObjectSubclass<imp::OwlPreferences>)
+        @extends gtk::Widget, gtk::Window,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget,
+                    gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl OwlPreferences {
+    pub fn new(app: &OwlApplication, parent: &impl IsA<gtk::Window>) -> Self {
+        let prefs: Self = glib::Object::builder()
+            .property("transient-for", parent)
+            .build();
+        prefs.imp().app.set(Some(app));
+        // Fill the widgets before connecting so loading does not write back
+        prefs.load_values();
+        prefs.setup_signals();
+        prefs
+    }
+
+    fn load_values(&self) {
+        let imp = self.imp();
+        let Some(app) = imp.app.upgrade() else {
+            return;
+        };
+        let settings = app.settings();
+        let s = settings.borrow();
+
+        imp.theme_entry.set_text(&s.theme);
+        imp.prefer_dark_switch.set_active(s.prefer_dark);
+        imp.view_mode_dropdown.set_selected(match s.view.view_mode {
+            ViewMode::List => 0,
+            ViewMode::Grid => 1,
+            ViewMode::Compact => 2,
+            ViewMode::Columns => 3,
+        });
+        imp.show_hidden_switch.set_active(s.show_hidden_files);
+        imp.show_thumbnails_switch.set_active(s.show_thumbnails);
+        imp.date_format_entry.set_text(&s.display.date_format);
+        imp.size_units_dropdown
+            .set_selected(match s.display.size_units {
+                SizeUnits::Binary => 0,
+                SizeUnits::Decimal => 1,
+            });
+        imp.single_click_switch.set_active(s.single_click);
+        imp.type_ahead_switch.set_active(s.type_ahead);
+        imp.confirm_delete_switch.set_active(s.confirm_delete);
+        imp.history_size_spin.set_value(s.history_size as f64);
+        imp.terminal_entry.set_text(&s.terminal);
+        imp.editor_entry.set_text(&s.editor);
+        imp.max_file_size_spin
+            .set_value(s.search.max_file_size_mb as f64);
+        imp.respect_gitignore_switch
+            .set_active(s.search.respect_gitignore);
+        imp.remember_views_switch
+            .set_active(s.remember_directory_views);
+    }
+
+    // Write one change to the settings file and apply it to every open window
+    fn update(&self, f: impl FnOnce(&mut Settings)) {
+        let Some(app) = self.imp().app.upgrade() else {
+            return;
+        };
+        {
+            let settings = app.settings();
+            let mut s = settings.borrow_mut();
+            f(&mut s);
+        }
+        app.save_settings_later();
+        app.apply_settings();
+    }
+
+    fn connect_switch<F: Fn(&mut Settings, bool) + 'static>(&self, switch: &gtk::Switch, f: F) {
+        switch.connect_active_notify(glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |switch| {
+                let active = switch.is_active();
+                prefs.update(|s| f(s, active));
+            }
+        ));
+    }
+
+    // Applied on Enter, when the entry loses focus or when the window closes,
+    // not on every keystroke, which would save and restyle all windows
+    fn connect_entry<F: Fn(&mut Settings, String) + 'static>(&self, entry: &gtk::Entry, f: F) {
+        let applied = RefCell::new(entry.text().to_string());
+        let apply = glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |entry: &gtk::Entry| {
+                let text = entry.text().to_string();
+                if *applied.borrow() == text {
+                    return;
+                }
+                *applied.borrow_mut() = text.clone();
+                prefs.update(|s| f(s, text));
+            }
+        );
+        let apply = Rc::new(apply);
+
+        entry.connect_activate(glib::clone!(
+            #[strong]
+            apply,
+            move |entry| apply(entry)
+        ));
+        let focus = gtk::EventControllerFocus::new();
+        focus.connect_leave(glib::clone!(
+            #[weak]
+            entry,
+            #[strong]
+            apply,
+            move |_| apply(&entry)
+        ));
+        entry.add_controller(focus);
+        self.connect_close_request(glib::clone!(
+            #[weak]
+            entry,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_| {
+                apply(&entry);
+                glib::Propagation::Proceed
+            }
+        ));
+    }
+
+    fn setup_signals(&self) {
+        let imp = self.imp();
+
+        // General
+        self.connect_entry(&imp.theme_entry, |s, text| s.theme = text);
+        self.connect_switch(&imp.prefer_dark_switch, |s, active| s.prefer_dark = active);
+
+        // Views
+        imp.view_mode_dropdown.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |dropdown| {
+                let mode = match dropdown.selected() {
+                    1 => ViewMode::Grid,
+                    2 => ViewMode::Compact,
+                    3 => ViewMode::Columns,
+                    _ => ViewMode::List,
+                };
+                prefs.update(|s| s.view.view_mode = mode);
+            }
+        ));
+        self.connect_switch(&imp.show_hidden_switch, |s, active| {
+            s.show_hidden_files = active
+        });
+        self.connect_switch(&imp.show_thumbnails_switch, |s, active| {
+            s.show_thumbnails = active
+        });
+        self.connect_entry(&imp.date_format_entry, |s, text| {
+            s.display.date_format = text
+        });
+        imp.size_units_dropdown
+            .connect_selected_notify(glib::clone!(
+                #[weak(rename_to = prefs)]
+                self,
+                move |dropdown| {
+                    let units = match dropdown.selected() {
+                        1 => SizeUnits::Decimal,
+                        _ => SizeUnits::Binary,
+                    };
+                    prefs.update(|s| s.display.size_units = units);
+                }
+            ));
+
+        // Behavior
+        self.connect_switch(&imp.single_click_switch, |s, active| {
+            s.single_click = active
+        });
+        self.connect_switch(&imp.type_ahead_switch, |s, active| s.type_ahead = active);
+        self.connect_switch(&imp.confirm_delete_switch, |s, active| {
+            s.confirm_delete = active
+        });
+        imp.history_size_spin.connect_value_changed(glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |spin| {
+                let size = spin.value_as_int().max(0) as usize;
+                prefs.update(|s| {
+                    s.history_size = size;
+                    s.recent_locations.truncate(size);
+                });
+            }
+        ));
+
+        // Programs
+        self.connect_entry(&imp.terminal_entry, |s, text| s.terminal = text);
+        self.connect_entry(&imp.editor_entry, |s, text| s.editor = text);
+
+        // Search
+        imp.max_file_size_spin.connect_value_changed(glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |spin| {
+                let size = spin.value_as_int().max(1) as u64;
+                prefs.update(|s| s.search.max_file_size_mb = size);
+            }
+        ));
+        self.connect_switch(&imp.respect_gitignore_switch, |s, active| {
+            s.search.respect_gitignore = active
+        });
+
+        // Advanced
+        self.connect_switch(&imp.remember_views_switch, |s, active| {
+            s.remember_directory_views = active
+        });
+        imp.forget_views_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = prefs)]
+            self,
+            move |_| prefs.update(|s| s.directories.clear())
+        ));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::file_entry::FileEntry;
+use crate::highlight::{self, Token};
+use crate::search;
+use gtk::gdk;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk4 as gtk;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// Only the start of larger text files is shown
+const MAX_TEXT_BYTES: usize = 256 * 1024;
+// Bytes of a binary file shown as a hex dump
+const HEX_BYTES: usize = 4096;
+// Larger images are not decoded
+const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+// Width in pixels of the rendered first page of a PDF
+const PDF_WIDTH: u32 = 1024;
+
+// What a file looks like, worked out off the main thread
+pub enum Preview {
+    Text {
+        text: String,
+        // Character ranges to style
+        highlights: Vec<(usize, usize, Token)>,
+        truncated: bool,
+    },
+    // Also the first page of a PDF
+    Image(gdk::Texture),
+    // A hex dump of the start of the file
+    Binary {
+        dump: String,
+        truncated: bool,
+    },
+    Folder {
+        folders: usize,
+        files: usize,
+        size: u64,
+    },
+    // Audio and video: named details such as the duration and codecs
+    Media(Vec<(String, String)>),
+    Message(String),
+}
+
+// Runs `load` on a worker thread and hands the result to `done` on the main loop
+pub fn load_async<F: FnOnce(Preview) + 'static>(entry: FileEntry, done: F) {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(load(&entry));
+    });
+    let mut done = Some(done);
+    glib::timeout_add_local(Duration::from_millis(50), move || {
+        match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Ok(preview) => {
+                if let Some(done) = done.take() {
+                    done(preview);
+                }
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        glib::ControlFlow::Break
+    });
+}
+
+// Reads `entry` for previewing; slow for big folders and images, so call it
+// from a worker thread
+pub fn load(entry: &FileEntry) -> Preview {
+    if entry.is_dir {
+        return load_folder(&entry.path);
+    }
+    let path = &entry.path;
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Preview::Message(e.to_string()),
+    };
+    let mut data = Vec::new();
+    let limit = MAX_TEXT_BYTES.max(HEX_BYTES) as u64;
+    if let Err(e) = file.by_ref().take(limit + 1).read_to_end(&mut data) {
+        return Preview::Message(e.to_string());
+    }
+    if data.is_empty() {
+        return Preview::Message("Empty file".to_string());
+    }
+
+    let (content_type, _) = gio::content_type_guess(Some(path), Some(&data[..]));
+    if gio::content_type_is_mime_type(&content_type, "application/pdf") {
+        return match render_pdf(path) {
+            Some(texture) => Preview::Image(texture),
+            None => Preview::Message(
+                "Install pdftoppm (poppler-utils) to preview PDF files".to_string(),
+            ),
+        };
+    }
+    if gio::content_type_is_mime_type(&content_type, "audio/*")
+        || gio::content_type_is_mime_type(&content_type, "video/*")
+    {
+        return Preview::Media(media_info(path));
+    }
+    if gio::content_type_is_mime_type(&content_type, "image/*") && entry.size <= MAX_IMAGE_BYTES {
+        // Formats GDK cannot decode fall through to the hex dump
+        if let Ok(texture) = gdk::Texture::from_filename(path) {
+            return Preview::Image(texture);
+        }
+    }
+
+    if search::is_text(path, &data) {
+        let truncated = data.len() > MAX_TEXT_BYTES;
+        data.truncate(MAX_TEXT_BYTES);
+        let text = String::from_utf8_lossy(&data).into_owned();
+        let highlights = highlight::highlight(&text, &entry.extension());
+        return Preview::Text {
+            text,
+            highlights,
+            truncated,
+        };
+    }
+
+    let truncated = data.len() > HEX_BYTES;
+    data.truncate(HEX_BYTES);
+    Preview::Binary {
+        dump: hex_dump(&data),
+        truncated,
+    }
+}
+
+// Direct children only; the size is that of the files among them
+fn load_folder(path: &Path) -> Preview {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => return Preview::Message(e.to_string()),
+    };
+    let (mut folders, mut files, mut size) = (0, 0, 0);
+    for entry in entries.filter_map(|e| e.ok()) {
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => folders += 1,
+            Ok(metadata) => {
+                files += 1;
+                size += metadata.len();
+            }
+            Err(_) => files += 1,
+        }
+    }
+    Preview::Folder {
+        folders,
+        files,
+        size,
+    }
+}
+
+// Sixteen bytes per line: offset, hex bytes and printable ASCII
+fn hex_dump(data: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        dump.push_str(&format!("{:08x}  ", line * 16));
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(byte) => dump.push_str(&format!("{:02x} ", byte)),
+                None => dump.push_str("   "),
+            }
+            if i == 7 {
+                dump.push(' ');
+            }
+        }
+        dump.push(' ');
+        dump.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        dump.push('\n');
+    }
+    dump
+}
+
+// First page through poppler's pdftoppm, if installed
+fn render_pdf(path: &Path) -> Option<gdk::Texture> {
+    // A private directory per preview (mkdtemp), so concurrent previews and
+    // other users cannot interfere with the output file
+    let dir = glib::dir_make_tmp(Some("owl-preview-XXXXXX")).ok()?;
+    let base = dir.join("page");
+    let status = Command::new("pdftoppm")
+        .args(["-png", "-singlefile", "-f", "1", "-l", "1", "-scale-to-x"])
+        .arg(PDF_WIDTH.to_string())
+        .args(["-scale-to-y", "-1"])
+        .arg(path)
+        .arg(&base)
+        .status();
+    let texture = status
+        .is_ok_and(|s| s.success())
+        .then(|| gdk::Texture::from_filename(base.with_extension("png")).ok())
+        .flatten();
+    let _ = fs::remove_dir_all(&dir);
+    texture
+}
+
+// Duration, tags and streams as reported by ffprobe; empty without it
+fn media_info(path: &Path) -> Vec<(String, String)> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-show_entries",
+            "format=duration,bit_rate:format_tags=title,artist,album\
+             :stream=codec_type,codec_name,width,height,sample_rate,channels",
+        ])
+        .arg(path)
+        .output();
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    // Sections look like "[STREAM]", then "key=value" lines, then "[/STREAM]"
+    let mut info = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if line.starts_with("[/") {
+            info.extend(describe_section(line, &fields));
+            fields.clear();
+        } else if let Some((key, value)) = line.split_once('=') {
+            fields.push((key.to_string(), value.to_string()));
+        }
+    }
+    info
+}
+
+fn describe_section(end: &str, fields: &[(String, String)]) -> Vec<(String, String)> {
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty() && *v != "N/A")
+    };
+    let mut info = Vec::new();
+    match end {
+        "[/FORMAT]" => {
+            for (key, label) in [
+                ("TAG:title", "Title"),
+                ("TAG:artist", "Artist"),
+                ("TAG:album", "Album"),
+            ] {
+                if let Some(value) = field(key) {
+                    info.push((label.to_string(), value.to_string()));
+                }
+            }
+            if let Some(seconds) = field("duration").and_then(|d| d.parse::<f64>().ok()) {
+                let seconds = seconds.round() as u64;
+                let duration = if seconds >= 3600 {
+                    format!(
+                        "{}:{:02}:{:02}",
+                        seconds / 3600,
+                        seconds / 60 % 60,
+                        seconds % 60
+                    )
+                } else {
+                    format!("{}:{:02}", seconds / 60, seconds % 60)
+                };
+                info.push(("Duration".to_string(), duration));
+            }
+            if let Some(rate) = field("bit_rate").and_then(|r| r.parse::<u64>().ok()) {
+                info.push(("Bit rate".to_string(), format!("{} kb/s", rate / 1000)));
+            }
+        }
+        "[/STREAM]" => {
+            let codec = field("codec_name").unwrap_or("unknown");
+            match field("codec_type") {
+                Some("video") => {
+                    let mut value = codec.to_string();
+                    if let (Some(w), Some(h)) = (field("width"), field("height")) {
+                        value.push_str(&format!(", {} × {}", w, h));
+                    }
+                    info.push(("Video".to_string(), value));
+                }
+                Some("audio") => {
+                    let mut value = codec.to_string();
+                    if let Some(rate) = field("sample_rate") {
+                        value.push_str(&format!(", {} Hz", rate));
+                    }
+                    if let Some(channels) = field("channels") {
+                        value.push_str(&format!(", {} channels", channels));
+                    }
+                    info.push(("Audio".to_string(), value));
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    info
+}
+
+// Text tags for the highlighted tokens, in colours that stay readable on
+// light and dark themes
+pub fn setup_tags(buffer: &gtk::TextBuffer) {
+    let table = buffer.tag_table();
+    let styles = [
+        (Token::Keyword, "#9b59b6", true),
+        (Token::String, "#3f9f3f", false),
+        (Token::Comment, "#888888", false),
+        (Token::Number, "#d4770c", false),
+    ];
+    for (token, colour, bold) in styles {
+        let tag = gtk::TextTag::new(Some(token.tag()));
+        tag.set_foreground(Some(colour));
+        if bold {
+            tag.set_weight(700);
+        }
+        if token == Token::Comment {
+            tag.set_style(gtk::pango::Style::Italic);
+        }
+        table.add(&tag);
+    }
+}
+
+// Replaces the text of a buffer set up with `setup_tags`
+pub fn set_text(buffer: &gtk::TextBuffer, text: &str, highlights: &[(usize, usize, Token)]) {
+    buffer.set_text(text);
+    for (start, end, token) in highlights {
+        let start = buffer.iter_at_offset(*start as i32);
+        let end = buffer.iter_at_offset(*end as i32);
+        buffer.apply_tag_by_name(token.tag(), &start, &end);
+    }
+}
+
+// Appends a name and value below the rows already in `grid`
+pub fn add_info_row(grid: &gtk::Grid, name: &str, value: &str) {
+    let mut row = 0;
+    while grid.child_at(0, row).is_some() {
+        row += 1;
+    }
+
+    let name = gtk::Label::new(Some(name));
+    name.set_xalign(1.0);
+    name.set_yalign(0.0);
+    name.add_css_class("dim-label");
+    grid.attach(&name, 0, row, 1, 1);
+
+    let value = gtk::Label::new(Some(value));
+    value.set_xalign(0.0);
+    value.set_hexpand(true);
+    value.set_wrap(true);
+    value.set_wrap_mode(gtk::pango::WrapMode::WordChar);
+    value.set_selectable(true);
+    grid.attach(&value, 1, row, 1, 1);
+}
@@ -0,0 +1,235 @@
+use crate::file_entry::{format_size, FileEntry};
+use crate::preview::{self, Preview};
+use crate::types::{Column, DisplayFormat};
+use crate::widgets::content_panel::OwlContentPanel;
+use gtk::gdk;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk4 as gtk;
+
+mod imp {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(file = "../data/quick_look.ui")]
+    pub struct OwlQuickLook {
+        #[template_child]
+        pub title_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub position_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub text_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub truncated_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub picture: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub message_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub info_grid: TemplateChild<gtk::Grid>,
+        // The panel's entries when the window opened; the arrow keys step through them
+        pub entries: RefCell<Vec<FileEntry>>,
+        pub index: Cell<usize>,
+        // Follows the shown entry with its selection
+        pub panel: glib::WeakRef<OwlContentPanel>,
+        pub format: RefCell<DisplayFormat>,
+        // Bumped for every shown entry; results of older loads are dropped
+        pub generation: Cell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwlQuickLook {
+        const NAME: &'static str = "OwlQuickLook";
+        type Type = super::OwlQuickLook;
+        type ParentType = gtk::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OwlQuickLook {
+        fn constructed(&self) {
+            self.parent_constructed();
+            preview::setup_tags(&self.text_view.buffer());
+        }
+    }
+
+    impl WidgetImpl for OwlQuickLook {}
+    impl WindowImpl for OwlQuickLook {}
+}
+
+glib::wrapper! {
+    pub struct OwlQuickLook(ObjectSubclass<imp::OwlQuickLook>)
+        @extends gtk::Widget, gtk::Window,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget,
+                    gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl OwlQuickLook {
+    // Shows `entries[index]` of `panel`
+    pub fn new(
+        parent: &impl IsA<gtk::Window>,
+        panel: &OwlContentPanel,
+        entries: Vec<FileEntry>,
+        index: usize,
+    ) -> Self {
+        let window: Self = glib::Object::builder()
+            .property("transient-for", parent)
+            .build();
+        let imp = window.imp();
+        *imp.entries.borrow_mut() = entries;
+        *imp.format.borrow_mut() = panel.display_format();
+        imp.panel.set(Some(panel));
+        window.setup_keys();
+        window.show_entry(index);
+        window
+    }
+
+    // Arrows step through the entries and Space or Escape closes; taken
+    // before the text view so they do not move its cursor
+    fn setup_keys(&self) {
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, _| {
+                let index = window.imp().index.get();
+                match keyval {
+                    gdk::Key::Left | gdk::Key::Up => {
+                        if index > 0 {
+                            window.show_entry(index - 1);
+                        }
+                    }
+                    gdk::Key::Right | gdk::Key::Down => window.show_entry(index + 1),
+                    gdk::Key::space | gdk::Key::Escape => window.close(),
+                    _ => return glib::Propagation::Proceed,
+                }
+                glib::Propagation::Stop
+            }
+        ));
+        self.add_controller(keys);
+    }
+
+    fn show_entry(&self, index: usize) {
+        let imp = self.imp();
+        let Some(entry) = imp.entries.borrow().get(index).cloned() else {
+            return;
+        };
+        imp.index.set(index);
+        let generation = imp.generation.get() + 1;
+        imp.generation.set(generation);
+
+        self.set_title(Some(&entry.name));
+        imp.title_label.set_text(&entry.name);
+        imp.position_label
+            .set_text(&format!("{} of {}", index + 1, imp.entries.borrow().len()));
+        if let Some(panel) = imp.panel.upgrade() {
+            panel.select_path(&entry.path);
+        }
+
+        // Basic details while the contents load
+        imp.icon.set_icon_name(Some(entry.icon_name()));
+        imp.message_label.set_text("");
+        imp.truncated_label.set_visible(false);
+        self.fill_info(&entry);
+        imp.stack.set_visible_child_name("info");
+
+        let size_units = imp.format.borrow().size_units;
+        preview::load_async(
+            entry,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |preview| {
+                    let imp = window.imp();
+                    if imp.generation.get() != generation {
+                        return;
+                    }
+                    let buffer = imp.text_view.buffer();
+                    match preview {
+                        Preview::Text {
+                            text,
+                            highlights,
+                            truncated,
+                        } => {
+                            preview::set_text(&buffer, &text, &highlights);
+                            window.show_truncated(truncated, "Only the start of the file is shown");
+                            imp.stack.set_visible_child_name("text");
+                        }
+                        Preview::Binary { dump, truncated } => {
+                            preview::set_text(&buffer, &dump, &[]);
+                            window.show_truncated(truncated, "Only the first bytes are shown");
+                            imp.stack.set_visible_child_name("text");
+                        }
+                        Preview::Image(texture) => {
+                            imp.picture.set_paintable(Some(&texture));
+                            imp.stack.set_visible_child_name("image");
+                        }
+                        Preview::Folder {
+                            folders,
+                            files,
+                            size,
+                        } => {
+                            imp.message_label.set_text(&format!(
+                                "{} folder{}, {} file{}, {} in files",
+                                folders,
+                                if folders == 1 { "" } else { "s" },
+                                files,
+                                if files == 1 { "" } else { "s" },
+                                format_size(size, size_units),
+                            ));
+                        }
+                        Preview::Media(info) => {
+                            if info.is_empty() {
+                                imp.message_label.set_text(
+                                    "Install ffprobe (FFmpeg) to see the duration and codecs",
+                                );
+                            }
+                            for (name, value) in info {
+                                preview::add_info_row(&imp.info_grid, &name, &value);
+                            }
+                        }
+                        Preview::Message(message) => imp.message_label.set_text(&message),
+                    }
+                }
+            ),
+        );
+    }
+
+    fn show_truncated(&self, truncated: bool, text: &str) {
+        let label = &self.imp().truncated_label;
+        label.set_text(text);
+        label.set_visible(truncated);
+    }
+
+    fn fill_info(&self, entry: &FileEntry) {
+        let imp = self.imp();
+        let grid = &imp.info_grid;
+        while let Some(child) = grid.first_child() {
+            grid.remove(&child);
+        }
+        let format = imp.format.borrow();
+        let size = if entry.is_dir {
+            Column::ItemCount
+        } else {
+            Column::Size
+        };
+        for column in [Column::Type, size, Column::Date] {
+            preview::add_info_row(grid, column.label(), &entry.column_display(column, &format));
+        }
+    }
+}
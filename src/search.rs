@@ -0,0 +1,448 @@
+use crate::file_entry::FileEntry;
+use crate::search_filters::{self, SearchFilters};
+use gtk::gio;
+use gtk::glib;
+use gtk4 as gtk;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Results are sent in batches so the UI is not woken up for every file
+const BATCH_SIZE: usize = 200;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+// Content search limits per file
+const MAX_HITS_PER_FILE: usize = 5;
+const SNIFF_SIZE: usize = 8192;
+const SNIPPET_CONTEXT: usize = 40;
+const SNIPPET_LENGTH: usize = 160;
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchMode {
+    #[default]
+    Substring,
+    Glob,
+    Regex,
+    // Text inside files rather than their names
+    Contents,
+}
+
+impl SearchMode {
+    pub fn id(&self) -> &'static str {
+        match self {
+            SearchMode::Substring => "substring",
+            SearchMode::Glob => "glob",
+            SearchMode::Regex => "regex",
+            SearchMode::Contents => "contents",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Self {
+        match id {
+            "glob" => SearchMode::Glob,
+            "regex" => SearchMode::Regex,
+            "contents" => SearchMode::Contents,
+            _ => SearchMode::Substring,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub mode: SearchMode,
+    pub case_sensitive: bool,
+    pub filters: SearchFilters,
+}
+
+impl SearchQuery {
+    // Separates `key:value` filters typed into the query from the text to match
+    pub fn parse(input: &str, mode: SearchMode, case_sensitive: bool) -> Result<Self, String> {
+        let (text, filters) = search_filters::parse(input)?;
+        Ok(Self {
+            text,
+            mode,
+            case_sensitive,
+            filters,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters.is_empty()
+    }
+
+    pub fn matcher(&self) -> Result<Matcher, String> {
+        // Only filters: every file is a candidate, even in contents mode
+        if self.text.is_empty() {
+            return Ok(Matcher::Substring(String::new(), false));
+        }
+        match self.mode {
+            SearchMode::Substring if self.case_sensitive => {
+                Ok(Matcher::Substring(self.text.clone(), true))
+            }
+            SearchMode::Substring => Ok(Matcher::Substring(self.text.to_lowercase(), false)),
+            SearchMode::Glob => self
+                .build_regex(&glob_to_regex(&self.text))
+                .map(Matcher::Regex),
+            SearchMode::Regex => self.build_regex(&self.text).map(Matcher::Regex),
+            SearchMode::Contents => self
+                .build_regex(&regex::escape(&self.text))
+                .map(Matcher::Contents),
+        }
+    }
+
+    fn build_regex(&self, pattern: &str) -> Result<Regex, String> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {}", e))
+    }
+}
+
+// Compiled form of a query, tested against file names or, for `Contents`,
+// against each line of text files
+pub enum Matcher {
+    Substring(String, bool),
+    Regex(Regex),
+    Contents(Regex),
+}
+
+impl Matcher {
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            Matcher::Substring(needle, true) => name.contains(needle.as_str()),
+            Matcher::Substring(needle, false) => name.to_lowercase().contains(needle.as_str()),
+            Matcher::Regex(re) => re.is_match(name),
+            Matcher::Contents(_) => false,
+        }
+    }
+}
+
+// Where walking stops and which files are worth reading
+#[derive(Clone, Default)]
+pub struct SearchOptions {
+    pub include_hidden: bool,
+    pub max_file_size: u64,
+    pub respect_gitignore: bool,
+    pub filters: SearchFilters,
+}
+
+// One matching line of a content search
+#[derive(Clone)]
+pub struct ContentHit {
+    pub line: u32,
+    // Excerpt of the line around the first hit
+    pub text: String,
+    // Byte ranges of the hits within `text`
+    pub ranges: Vec<(usize, usize)>,
+}
+
+impl ContentHit {
+    fn new(line: usize, text: &str, ranges: Vec<(usize, usize)>) -> Self {
+        let mut start = ranges[0].0.saturating_sub(SNIPPET_CONTEXT);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (start + SNIPPET_LENGTH).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+
+        Self {
+            line: line as u32,
+            text: text[start..end].to_string(),
+            ranges: ranges
+                .into_iter()
+                .filter(|(s, _)| *s >= start && *s < end)
+                .map(|(s, e)| (s - start, e.min(end) - start))
+                .collect(),
+        }
+    }
+
+    // Pango markup of the excerpt with the hits in bold
+    pub fn markup(&self) -> String {
+        let mut out = String::new();
+        let mut pos = 0;
+        for (start, end) in &self.ranges {
+            out.push_str(&glib::markup_escape_text(&self.text[pos..*start]));
+            out.push_str("<b>");
+            out.push_str(&glib::markup_escape_text(&self.text[*start..*end]));
+            out.push_str("</b>");
+            pos = *end;
+        }
+        out.push_str(&glib::markup_escape_text(&self.text[pos..]));
+        out
+    }
+}
+
+pub struct SearchResult {
+    pub entry: FileEntry,
+    // Empty unless searching contents
+    pub hits: Vec<ContentHit>,
+}
+
+// Dropping the handle does not stop the search, `cancel` does
+#[derive(Clone)]
+pub struct SearchHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SearchHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// Walks `root` on a background thread; the receiver disconnects when the walk ends
+pub fn spawn(
+    root: PathBuf,
+    matcher: Matcher,
+    options: SearchOptions,
+) -> (SearchHandle, mpsc::Receiver<Vec<SearchResult>>) {
+    let handle = SearchHandle {
+        cancelled: Arc::new(AtomicBool::new(false)),
+    };
+    let (tx, rx) = mpsc::channel();
+
+    let thread_handle = handle.clone();
+    std::thread::spawn(move || walk(root, matcher, options, thread_handle, tx));
+
+    (handle, rx)
+}
+
+fn walk(
+    root: PathBuf,
+    matcher: Matcher,
+    options: SearchOptions,
+    handle: SearchHandle,
+    tx: mpsc::Sender<Vec<SearchResult>>,
+) {
+    // Ignore rules only matter when reading contents
+    let use_gitignore = options.respect_gitignore && matches!(matcher, Matcher::Contents(_));
+    let mut pending = vec![(root, 1, Vec::new())];
+    let mut batch = Vec::new();
+    let mut last_flush = Instant::now();
+
+    while let Some((dir, depth, mut rules)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        if use_gitignore {
+            rules.extend(read_gitignore(&dir));
+        }
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            if handle.is_cancelled() {
+                return;
+            }
+            if batch.len() >= BATCH_SIZE
+                || (!batch.is_empty() && last_flush.elapsed() >= BATCH_INTERVAL)
+            {
+                if tx.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+                last_flush = Instant::now();
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !options.include_hidden && name.starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            // Do not follow symlinked directories to avoid cycles
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if use_gitignore && (name == ".git" || is_ignored(&rules, &path, &name, is_dir)) {
+                continue;
+            }
+            if is_dir && options.filters.max_depth.is_none_or(|max| depth < max) {
+                pending.push((path.clone(), depth + 1, rules.clone()));
+            }
+
+            let contents = match &matcher {
+                Matcher::Contents(_) if is_dir => continue,
+                Matcher::Contents(re) => Some(re),
+                _ if matcher.is_match(&name) => None,
+                _ => continue,
+            };
+            // Filters need metadata, so check them before reading any contents
+            let Some(found) = FileEntry::from_path(path) else {
+                continue;
+            };
+            if !options.filters.matches(&found) {
+                continue;
+            }
+            let hits = match contents {
+                Some(re) => match scan_file(&found.path, re, options.max_file_size) {
+                    Some(hits) if !hits.is_empty() => hits,
+                    _ => continue,
+                },
+                None => Vec::new(),
+            };
+            batch.push(SearchResult { entry: found, hits });
+        }
+    }
+
+    if !batch.is_empty() {
+        let _ = tx.send(batch);
+    }
+}
+
+// Matching lines of a text file, None for binaries and files over the size limit
+fn scan_file(path: &Path, re: &Regex, max_size: u64) -> Option<Vec<ContentHit>> {
+    let mut file = fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() > max_size {
+        return None;
+    }
+    // Binaries are recognised from their start without reading them whole
+    let mut data = Vec::new();
+    file.by_ref()
+        .take(SNIFF_SIZE as u64)
+        .read_to_end(&mut data)
+        .ok()?;
+    if !is_text(path, &data) {
+        return None;
+    }
+    file.read_to_end(&mut data).ok()?;
+
+    let hits = String::from_utf8_lossy(&data)
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let ranges: Vec<(usize, usize)> =
+                re.find_iter(line).map(|m| (m.start(), m.end())).collect();
+            (!ranges.is_empty()).then(|| ContentHit::new(i + 1, line, ranges))
+        })
+        .take(MAX_HITS_PER_FILE)
+        .collect();
+    Some(hits)
+}
+
+// NUL bytes mean binary; otherwise trust the MIME type or valid UTF-8
+pub fn is_text(path: &Path, data: &[u8]) -> bool {
+    let sample = &data[..data.len().min(SNIFF_SIZE)];
+    if sample.contains(&0) {
+        return false;
+    }
+    let (content_type, _) = gio::content_type_guess(Some(path), Some(sample));
+    if gio::content_type_is_a(&content_type, "text/plain") {
+        return true;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        // A multi-byte character cut off by the sample size
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+// One pattern of a .gitignore file
+#[derive(Clone)]
+struct IgnoreRule {
+    base: PathBuf,
+    regex: Regex,
+    // Patterns containing a slash match the path relative to `base`
+    anchored: bool,
+    dir_only: bool,
+    negate: bool,
+}
+
+fn read_gitignore(dir: &Path) -> Vec<IgnoreRule> {
+    let Ok(content) = fs::read_to_string(dir.join(".gitignore")) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (negate, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/');
+            let regex = Regex::new(&glob_to_regex(pattern)).ok()?;
+            Some(IgnoreRule {
+                base: dir.to_path_buf(),
+                regex,
+                anchored,
+                dir_only,
+                negate,
+            })
+        })
+        .collect()
+}
+
+// The last matching rule wins, so a later `!pattern` re-includes a path
+fn is_ignored(rules: &[IgnoreRule], path: &Path, name: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        let matched = if rule.anchored {
+            path.strip_prefix(&rule.base)
+                .map(|rel| rule.regex.is_match(&rel.to_string_lossy()))
+                .unwrap_or(false)
+        } else {
+            rule.regex.is_match(name)
+        };
+        if matched {
+            ignored = !rule.negate;
+        }
+    }
+    ignored
+}
+
+// Translates shell wildcards (`*`, `?`, `[abc]`, `[!abc]`) into an anchored regex
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    let mut chars = glob.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                let mut class = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' && !class.is_empty() {
+                        closed = true;
+                        break;
+                    }
+                    class.push(c);
+                }
+                if closed {
+                    let (negate, class) = match class.strip_prefix('!') {
+                        Some(rest) => ("^", rest.to_string()),
+                        None => ("", class),
+                    };
+                    let class = class.replace('\\', "\\\\").replace('[', "\\[");
+                    re.push_str(&format!("[{}{}]", negate, class));
+                } else {
+                    re.push_str(&regex::escape(&format!("[{}", class)));
+                }
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    re.push('$');
+    re
+}
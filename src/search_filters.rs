@@ -0,0 +1,441 @@
+use crate::file_entry::FileEntry;
+use gtk::glib;
+use gtk4 as gtk;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY: u64 = 24 * 60 * 60;
+
+// Broad file categories derived from the MIME type
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Folder,
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Text,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 7] = [
+        Kind::Folder,
+        Kind::Image,
+        Kind::Video,
+        Kind::Audio,
+        Kind::Document,
+        Kind::Archive,
+        Kind::Text,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Kind::Folder => "folder",
+            Kind::Image => "image",
+            Kind::Video => "video",
+            Kind::Audio => "audio",
+            Kind::Document => "document",
+            Kind::Archive => "archive",
+            Kind::Text => "text",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.id() == id)
+    }
+
+    fn matches(&self, entry: &FileEntry) -> bool {
+        if entry.is_dir {
+            return *self == Kind::Folder;
+        }
+        let mime = entry.mime_type();
+        match self {
+            Kind::Folder => false,
+            Kind::Image => mime.starts_with("image/"),
+            Kind::Video => mime.starts_with("video/"),
+            Kind::Audio => mime.starts_with("audio/"),
+            Kind::Text => mime.starts_with("text/"),
+            Kind::Document => {
+                matches!(
+                    mime.as_str(),
+                    "application/pdf"
+                        | "application/msword"
+                        | "application/rtf"
+                        | "application/epub+zip"
+                        | "application/vnd.ms-excel"
+                        | "application/vnd.ms-powerpoint"
+                ) || mime.starts_with("application/vnd.oasis.opendocument")
+                    || mime.starts_with("application/vnd.openxmlformats-officedocument")
+            }
+            Kind::Archive => {
+                matches!(
+                    mime.as_str(),
+                    "application/zip"
+                        | "application/x-tar"
+                        | "application/gzip"
+                        | "application/x-bzip2"
+                        | "application/x-xz"
+                        | "application/zstd"
+                        | "application/x-7z-compressed"
+                        | "application/vnd.rar"
+                        | "application/x-rar"
+                        | "application/x-iso9660-image"
+                ) || mime.contains("compressed")
+            }
+        }
+    }
+}
+
+// Inclusive bounds, either of which may be open
+#[derive(Clone, PartialEq, Debug)]
+pub struct Range<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: PartialOrd> Range<T> {
+    fn contains(&self, value: &T) -> bool {
+        self.min.as_ref().is_none_or(|min| value >= min)
+            && self.max.as_ref().is_none_or(|max| value <= max)
+    }
+}
+
+// Permission test on the mode bits
+#[derive(Clone, PartialEq, Debug)]
+pub enum Permissions {
+    // `perm:644`, compares the rwx bits exactly
+    Exact(u32),
+    // `perm:x` or `perm:rw`, the owner has at least these
+    Owner(u32),
+}
+
+// Structured conditions a search result must meet besides the query text
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct SearchFilters {
+    pub size: Vec<Range<u64>>,
+    pub modified: Vec<Range<SystemTime>>,
+    // Any of these kinds
+    pub kinds: Vec<Kind>,
+    pub owner: Option<String>,
+    pub permissions: Vec<Permissions>,
+    // 1 only looks at the searched folder itself
+    pub max_depth: Option<usize>,
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Depth is enforced by the walker, everything else here
+    pub fn matches(&self, entry: &FileEntry) -> bool {
+        // Folders have no meaningful size, so any size filter excludes them
+        if !self.size.is_empty()
+            && (entry.is_dir || !self.size.iter().all(|r| r.contains(&entry.size)))
+        {
+            return false;
+        }
+        if !self.modified.iter().all(|r| r.contains(&entry.modified)) {
+            return false;
+        }
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|k| k.matches(entry)) {
+            return false;
+        }
+        if let Some(owner) = &self.owner {
+            if entry.owner_display() != *owner && entry.uid.to_string() != *owner {
+                return false;
+            }
+        }
+        self.permissions.iter().all(|p| match p {
+            Permissions::Exact(bits) => entry.mode & 0o7777 == *bits,
+            Permissions::Owner(bits) => entry.mode & bits == *bits,
+        })
+    }
+}
+
+// Splits `key:value` filters out of the query; words with unknown keys stay
+// part of the text so names such as "http:" can still be searched for.
+//
+//   size:>10MB  size:<1G  size:1M..10M   (a bare size means at least that)
+//   modified:<2w            changed within the last two weeks
+//   modified:>1y            not changed for a year
+//   modified:>2024-01-31    after a date, `<` before it, `a..b` between
+//   modified:today          also `yesterday` or a bare date for that day
+//   type:image              folder, image, video, audio, document, archive, text
+//   owner:alice  perm:755  perm:x  depth:2
+pub fn parse(input: &str) -> Result<(String, SearchFilters), String> {
+    let mut filters = SearchFilters::default();
+    let mut words = Vec::new();
+
+    for word in input.split_whitespace() {
+        let Some((key, value)) = word.split_once(':') else {
+            words.push(word);
+            continue;
+        };
+        let invalid = || format!("Invalid filter \"{}\"", word);
+        match key.to_lowercase().as_str() {
+            "size" => filters
+                .size
+                .push(parse_size_range(value).ok_or_else(invalid)?),
+            "modified" | "mtime" => filters
+                .modified
+                .push(parse_time_range(value).ok_or_else(invalid)?),
+            "type" | "kind" => {
+                for id in value.split(',') {
+                    let kind = Kind::from_id(&id.to_lowercase()).ok_or_else(invalid)?;
+                    filters.kinds.push(kind);
+                }
+            }
+            "owner" if !value.is_empty() => filters.owner = Some(value.to_string()),
+            "perm" => filters
+                .permissions
+                .push(parse_permissions(value).ok_or_else(invalid)?),
+            "depth" => filters.max_depth = Some(value.parse().map_err(|_| invalid())?),
+            _ => words.push(word),
+        }
+    }
+
+    Ok((words.join(" "), filters))
+}
+
+fn parse_size_range(value: &str) -> Option<Range<u64>> {
+    if let Some((min, max)) = value.split_once("..") {
+        return Some(Range {
+            min: Some(parse_size(min)?),
+            max: Some(parse_size(max)?),
+        });
+    }
+    if let Some(max) = value.strip_prefix('<') {
+        return Some(Range {
+            min: None,
+            max: Some(parse_size(max)?),
+        });
+    }
+    let min = value.strip_prefix('>').unwrap_or(value);
+    Some(Range {
+        min: Some(parse_size(min)?),
+        max: None,
+    })
+}
+
+// "10", "10k", "1.5MB", "2GiB"; units are powers of 1024
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let factor: u64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * factor as f64) as u64)
+}
+
+fn parse_time_range(value: &str) -> Option<Range<SystemTime>> {
+    let now = SystemTime::now();
+    if let Some((from, to)) = value.split_once("..") {
+        return Some(Range {
+            min: Some(parse_date(from)?),
+            max: Some(parse_date(to)?.checked_add(Duration::from_secs(DAY))?),
+        });
+    }
+    // Durations are ages: `<2w` is newer than two weeks ago
+    if let Some(rest) = value.strip_prefix('<') {
+        return Some(match parse_age(rest) {
+            Some(age) => Range {
+                min: Some(ago(now, age)?),
+                max: None,
+            },
+            None => Range {
+                min: None,
+                max: Some(parse_date(rest)?),
+            },
+        });
+    }
+    if let Some(rest) = value.strip_prefix('>') {
+        return Some(match parse_age(rest) {
+            Some(age) => Range {
+                min: None,
+                max: Some(ago(now, age)?),
+            },
+            None => Range {
+                min: Some(parse_date(rest)?.checked_add(Duration::from_secs(DAY))?),
+                max: None,
+            },
+        });
+    }
+    if let Some(age) = parse_age(value) {
+        return Some(Range {
+            min: Some(ago(now, age)?),
+            max: None,
+        });
+    }
+    let day = parse_date(value)?;
+    Some(Range {
+        min: Some(day),
+        max: Some(day.checked_add(Duration::from_secs(DAY))?),
+    })
+}
+
+// `age` before `now`; like dates, nothing before the year 1 can be meant
+fn ago(now: SystemTime, age: Duration) -> Option<SystemTime> {
+    const YEAR_ONE_TO_EPOCH: u64 = 62_135_596_800;
+    let time = now.checked_sub(age)?;
+    let earliest = UNIX_EPOCH.checked_sub(Duration::from_secs(YEAR_ONE_TO_EPOCH))?;
+    (time >= earliest).then_some(time)
+}
+
+// "30m", "12h", "7d", "2w", "6mo", "1y"
+fn parse_age(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => DAY,
+        "w" => 7 * DAY,
+        "mo" => 30 * DAY,
+        "y" => 365 * DAY,
+        _ => return None,
+    };
+    // Ages too large to count are an invalid filter, not a crash
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+// Local midnight of "YYYY-MM-DD", "today" or "yesterday"
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let midnight = |dt: glib::DateTime| {
+        glib::DateTime::from_local(dt.year(), dt.month(), dt.day_of_month(), 0, 0, 0.0).ok()
+    };
+    let date = match value {
+        "today" => midnight(glib::DateTime::now_local().ok()?)?,
+        "yesterday" => midnight(glib::DateTime::now_local().ok()?.add_days(-1).ok()?)?,
+        _ => {
+            let mut parts = value.splitn(3, '-').map(|p| p.parse::<i32>().ok());
+            let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+            glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()?
+        }
+    };
+    let secs = u64::try_from(date.to_unix()).ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+fn parse_permissions(value: &str) -> Option<Permissions> {
+    if !value.is_empty() && value.chars().all(|c| c.is_digit(8)) && value.len() <= 4 {
+        return u32::from_str_radix(value, 8).ok().map(Permissions::Exact);
+    }
+    let mut bits = 0;
+    for c in value.trim_start_matches('+').chars() {
+        bits |= match c {
+            'r' => 0o400,
+            'w' => 0o200,
+            'x' => 0o100,
+            _ => return None,
+        };
+    }
+    (bits != 0).then_some(Permissions::Owner(bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(query: &str) -> SearchFilters {
+        parse(query).unwrap().1
+    }
+
+    #[test]
+    fn unknown_keys_stay_in_the_text() {
+        let (text, filters) = parse("report http:x size:>1k").unwrap();
+        assert_eq!(text, "report http:x");
+        assert_eq!(filters.size.len(), 1);
+    }
+
+    #[test]
+    fn size_units() {
+        assert_eq!(parse_size("10"), Some(10));
+        assert_eq!(parse_size("10k"), Some(10 << 10));
+        assert_eq!(parse_size("1.5MB"), Some(3 << 19));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1T"), Some(1 << 40));
+        assert_eq!(parse_size("3q"), None);
+        assert_eq!(parse_size("MB"), None);
+    }
+
+    #[test]
+    fn size_ranges() {
+        let range = |min, max| Range { min, max };
+        assert_eq!(
+            filters("size:1M..10M").size,
+            [range(Some(1 << 20), Some(10 << 20))]
+        );
+        assert_eq!(filters("size:<1G").size, [range(None, Some(1 << 30))]);
+        assert_eq!(filters("size:>10MB").size, [range(Some(10 << 20), None)]);
+        assert_eq!(filters("size:5k").size, [range(Some(5 << 10), None)]);
+        assert!(parse("size:1M..").is_err());
+    }
+
+    #[test]
+    fn modified_ages_and_dates() {
+        // A duration is an age: `<` is newer than it, `>` older
+        let newer = &filters("modified:<2w").modified[0];
+        assert!(newer.min.is_some() && newer.max.is_none());
+        let older = &filters("modified:>1y").modified[0];
+        assert!(older.min.is_none() && older.max.is_some());
+
+        // A date is a day: `<` is before it, `>` after it
+        let day = parse_date("2024-01-31").unwrap();
+        let next_day = day + Duration::from_secs(DAY);
+        let before = &filters("modified:<2024-01-31").modified[0];
+        assert_eq!((before.min, before.max), (None, Some(day)));
+        let after = &filters("modified:>2024-01-31").modified[0];
+        assert_eq!((after.min, after.max), (Some(next_day), None));
+        let on = &filters("modified:2024-01-31").modified[0];
+        assert_eq!((on.min, on.max), (Some(day), Some(next_day)));
+
+        let between = &filters("modified:2024-01-01..2024-01-31").modified[0];
+        assert_eq!(
+            (between.min, between.max),
+            (parse_date("2024-01-01"), Some(next_day))
+        );
+    }
+
+    #[test]
+    fn huge_ages_are_invalid() {
+        assert!(parse("modified:>99999999999y").is_err());
+        assert!(parse("modified:<99999999999999999999d").is_err());
+        assert_eq!(parse_age("18446744073709551615y"), None);
+        assert!(parse("modified:>60y").is_ok());
+    }
+
+    #[test]
+    fn permissions() {
+        assert_eq!(filters("perm:755").permissions, [Permissions::Exact(0o755)]);
+        assert_eq!(
+            filters("perm:4755").permissions,
+            [Permissions::Exact(0o4755)]
+        );
+        assert_eq!(filters("perm:x").permissions, [Permissions::Owner(0o100)]);
+        assert_eq!(filters("perm:+rw").permissions, [Permissions::Owner(0o600)]);
+        assert!(parse("perm:789").is_err());
+        assert!(parse("perm:q").is_err());
+        assert!(parse("perm:").is_err());
+    }
+
+    #[test]
+    fn kinds_and_depth() {
+        let filters = filters("type:image,video depth:2 owner:alice");
+        assert_eq!(filters.kinds, [Kind::Image, Kind::Video]);
+        assert_eq!(filters.max_depth, Some(2));
+        assert_eq!(filters.owner.as_deref(), Some("alice"));
+        assert!(parse("type:spreadsheet").is_err());
+        assert!(parse("depth:deep").is_err());
+    }
+}
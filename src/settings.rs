@@ -0,0 +1,303 @@
+use crate::search::SearchMode;
+use crate::types::{ColumnState, DisplayFormat, SortBy, SortOptions, SortOrder, ViewMode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// View state that can be remembered per directory
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub view_mode: ViewMode,
+    pub sort_by: SortBy,
+    pub sort_order: SortOrder,
+    pub columns: Vec<ColumnState>,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self {
+            view_mode: ViewMode::default(),
+            sort_by: SortBy::default(),
+            sort_order: SortOrder::default(),
+            columns: ColumnState::defaults(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+    pub paned_position: i32,
+    pub show_preview: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            maximized: false,
+            paned_position: 200,
+            show_preview: false,
+        }
+    }
+}
+
+// Limits for searching inside files
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSettings {
+    // Larger files are not scanned
+    pub max_file_size_mb: u64,
+    pub respect_gitignore: bool,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            max_file_size_mb: 10,
+            respect_gitignore: true,
+        }
+    }
+}
+
+// How often and how lately a folder was opened, for the jump palette
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Visit {
+    pub count: u32,
+    // Seconds since the Unix epoch
+    pub last: u64,
+}
+
+impl Visit {
+    // Frecency as in zoxide: the visit count weighted by how recent the last one was
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let weight = match age {
+            a if a < 60 * 60 => 4.0,
+            a if a < 24 * 60 * 60 => 2.0,
+            a if a < 7 * 24 * 60 * 60 => 0.5,
+            _ => 0.25,
+        };
+        self.count as f64 * weight
+    }
+}
+
+// Folders kept in `visits`; the least frecent ones are dropped beyond this
+const MAX_VISITS: usize = 1000;
+
+// A named query shown as a virtual folder in the sidebar
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub root: PathBuf,
+    // Text including any `key:value` filters
+    pub query: String,
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+// Everything stored in $XDG_CONFIG_HOME/owl/settings.toml
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // GTK theme name, empty to follow the system
+    pub theme: String,
+    pub prefer_dark: bool,
+    pub single_click: bool,
+    // Typing in a view jumps to matching names instead of opening the filter bar
+    pub type_ahead: bool,
+    // Shift+Delete asks before deleting the selection for good
+    pub confirm_delete: bool,
+    // Command template, `%d` is the directory; empty to auto-detect
+    pub terminal: String,
+    // Command template, `%f` is the file and `%l` the line; empty to auto-detect
+    pub editor: String,
+    pub show_hidden_files: bool,
+    // Images and documents show a preview instead of their type's icon
+    pub show_thumbnails: bool,
+    // Folders in the list view expand in place
+    pub tree_view: bool,
+    pub remember_directory_views: bool,
+    pub display: DisplayFormat,
+    pub sort_options: SortOptions,
+    pub search: SearchSettings,
+    pub window: WindowSettings,
+    // Used for directories without an entry in `directories`
+    pub view: ViewSettings,
+    pub directories: BTreeMap<String, ViewSettings>,
+    pub saved_searches: Vec<SavedSearch>,
+    // Folders visited in any window, most recent first
+    pub recent_locations: Vec<PathBuf>,
+    // How many recent locations are kept; 0 keeps none
+    pub history_size: usize,
+    // Visits by folder, ranking the jump palette
+    pub visits: BTreeMap<String, Visit>,
+    // Key bindings by detailed action name, replacing the defaults in
+    // shortcuts.rs, e.g. "win.go-back" = ["<Alt>Left", "BackSpace"]
+    pub shortcuts: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: "Adwaita".to_string(),
+            prefer_dark: false,
+            single_click: true,
+            type_ahead: true,
+            confirm_delete: true,
+            terminal: String::new(),
+            editor: String::new(),
+            show_hidden_files: false,
+            show_thumbnails: true,
+            tree_view: false,
+            remember_directory_views: true,
+            display: DisplayFormat::default(),
+            sort_options: SortOptions::default(),
+            search: SearchSettings::default(),
+            window: WindowSettings::default(),
+            view: ViewSettings::default(),
+            directories: BTreeMap::new(),
+            saved_searches: Vec::new(),
+            recent_locations: Vec::new(),
+            history_size: 50,
+            visits: BTreeMap::new(),
+            shortcuts: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn config_dir() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|d| d.config_dir().join("owl"))
+    }
+
+    fn file() -> Option<PathBuf> {
+        Self::config_dir().map(|d| d.join("settings.toml"))
+    }
+
+    pub fn load() -> Self {
+        let Some(content) = Self::file().and_then(|p| std::fs::read_to_string(p).ok()) else {
+            return Self::default();
+        };
+
+        let mut settings: Settings = match toml::from_str(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Ignoring invalid settings file: {}", e);
+                return Self::default();
+            }
+        };
+
+        ColumnState::normalize(&mut settings.view.columns);
+        for view in settings.directories.values_mut() {
+            ColumnState::normalize(&mut view.columns);
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::file() else {
+            return;
+        };
+        let Ok(content) = toml::to_string_pretty(self) else {
+            return;
+        };
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, content);
+    }
+
+    pub fn view_for(&self, path: &Path) -> ViewSettings {
+        self.directories
+            .get(path.to_string_lossy().as_ref())
+            .unwrap_or(&self.view)
+            .clone()
+    }
+
+    // Remember the view for this directory only
+    pub fn set_view_for(&mut self, path: &Path, view: ViewSettings) {
+        self.directories
+            .insert(path.to_string_lossy().to_string(), view);
+    }
+
+    // The view of directories without one of their own
+    pub fn set_default_view(&mut self, view: ViewSettings) {
+        self.view = view;
+    }
+
+    // Moves `path` to the front of the recent locations and counts the visit
+    pub fn record_location(&mut self, path: &Path) {
+        self.recent_locations.retain(|p| p != path);
+        self.recent_locations.insert(0, path.to_path_buf());
+        self.recent_locations.truncate(self.history_size);
+
+        let now = unix_now();
+        let visit = self
+            .visits
+            .entry(path.to_string_lossy().to_string())
+            .or_insert(Visit {
+                count: 0,
+                last: now,
+            });
+        visit.count += 1;
+        visit.last = now;
+        if self.visits.len() > MAX_VISITS {
+            let least = self
+                .visits
+                .iter()
+                .min_by(|a, b| a.1.score(now).total_cmp(&b.1.score(now)))
+                .map(|(path, _)| path.clone());
+            if let Some(least) = least {
+                self.visits.remove(&least);
+            }
+        }
+    }
+
+    // Visited folders that still exist, most frecent first
+    pub fn frecent_locations(&self) -> Vec<PathBuf> {
+        let now = unix_now();
+        let mut visits: Vec<(&String, f64)> = self
+            .visits
+            .iter()
+            .map(|(path, visit)| (path, visit.score(now)))
+            .collect();
+        visits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        visits
+            .into_iter()
+            .map(|(path, _)| PathBuf::from(path))
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    // Adds a saved search, replacing one with the same name
+    pub fn save_search(&mut self, search: SavedSearch) {
+        match self
+            .saved_searches
+            .iter_mut()
+            .find(|s| s.name == search.name)
+        {
+            Some(existing) => *existing = search,
+            None => self.saved_searches.push(search),
+        }
+    }
+
+    pub fn remove_saved_search(&mut self, name: &str) {
+        self.saved_searches.retain(|s| s.name != name);
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
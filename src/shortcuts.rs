@@ -0,0 +1,228 @@
+use crate::settings::Settings;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk4 as gtk;
+
+// A default key binding; `action` is a detailed action name such as
+// "win.view::'grid'", the same key used to remap it in the settings file
+pub struct Shortcut {
+    pub action: &'static str,
+    pub title: &'static str,
+    pub accels: &'static [&'static str],
+}
+
+const fn shortcut(
+    action: &'static str,
+    title: &'static str,
+    accels: &'static [&'static str],
+) -> Shortcut {
+    Shortcut {
+        action,
+        title,
+        accels,
+    }
+}
+
+// Default bindings by section, in the order the shortcuts window shows them
+pub const GROUPS: &[(&str, &[Shortcut])] = &[
+    (
+        "Split View",
+        &[
+            shortcut("win.split-view", "Split view", &["F3"]),
+            shortcut("win.copy-to-other-pane", "Copy to the other pane", &["F5"]),
+            shortcut("win.move-to-other-pane", "Move to the other pane", &["F6"]),
+            shortcut("win.sync-panes", "Same folder in both panes", &["<Alt>i"]),
+            shortcut("win.swap-panes", "Swap panes", &["<Control>u"]),
+        ],
+    ),
+    (
+        "Navigation",
+        &[
+            shortcut("win.go-back", "Back", &["<Alt>Left"]),
+            shortcut("win.go-forward", "Forward", &["<Alt>Right"]),
+            shortcut("win.go-parent", "Open parent folder", &["<Alt>Up"]),
+            shortcut("win.home", "Go to the personal folder", &["<Alt>Home"]),
+            shortcut("win.location", "Enter a location", &["<Control>l"]),
+            shortcut("win.jump", "Jump to a folder", &["<Control>p"]),
+            shortcut("win.refresh", "Reload", &["<Control>r"]),
+        ],
+    ),
+    (
+        "View",
+        &[
+            shortcut("win.view::'list'", "List view", &["<Control>1"]),
+            shortcut("win.view::'grid'", "Grid view", &["<Control>2"]),
+            shortcut("win.view::'compact'", "Compact view", &["<Control>3"]),
+            shortcut("win.view::'columns'", "Columns view", &["<Control>4"]),
+            shortcut(
+                "win.tree-view",
+                "Expandable folders in the list view",
+                &["<Control>e"],
+            ),
+            shortcut("win.show-hidden", "Show hidden files", &["<Control>h"]),
+            shortcut("win.preview", "Preview pane", &["F9"]),
+            shortcut("win.filter", "Filter this folder", &["<Control>f"]),
+            shortcut("win.search", "Search", &["<Control><Shift>f"]),
+        ],
+    ),
+    (
+        "Files",
+        &[
+            shortcut("win.copy", "Copy", &["<Control>c"]),
+            shortcut("win.cut", "Cut", &["<Control>x"]),
+            shortcut("win.paste", "Paste", &["<Control>v"]),
+            shortcut("win.select-all", "Select all", &["<Control>a"]),
+            shortcut("win.rename", "Rename", &["F2"]),
+            shortcut("win.trash", "Move to trash", &["Delete"]),
+            shortcut("win.delete", "Delete permanently", &["<Shift>Delete"]),
+        ],
+    ),
+    (
+        "Tabs and Windows",
+        &[
+            shortcut("win.new-tab", "New tab", &["<Control>t"]),
+            shortcut("win.close-tab", "Close tab", &["<Control>w"]),
+            shortcut(
+                "win.next-tab",
+                "Next tab",
+                &["<Control>Tab", "<Control>Page_Down"],
+            ),
+            shortcut(
+                "win.previous-tab",
+                "Previous tab",
+                &["<Control><Shift>Tab", "<Control>Page_Up"],
+            ),
+            shortcut("win.new-window", "New window", &["<Control>n"]),
+            shortcut(
+                "win.open-terminal",
+                "Open terminal here",
+                &["<Control><Alt>t"],
+            ),
+            shortcut("win.preferences", "Preferences", &["<Control>comma"]),
+            shortcut(
+                "win.command-palette",
+                "All commands",
+                &["<Control><Shift>p"],
+            ),
+            shortcut(
+                "win.help-index",
+                "Keyboard shortcuts",
+                &["<Control>question", "F1"],
+            ),
+        ],
+    ),
+];
+
+// Bindings for `action`, the user's if the settings remap it
+pub fn accels(settings: &Settings, action: &str) -> Vec<String> {
+    if let Some(accels) = settings.shortcuts.get(action) {
+        return accels.clone();
+    }
+    GROUPS
+        .iter()
+        .flat_map(|(_, shortcuts)| shortcuts.iter())
+        .find(|s| s.action == action)
+        .map(|s| s.accels.iter().map(|a| a.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// Registers the default bindings merged with the `[shortcuts]` table of the
+// settings file, where an empty list unbinds an action. Actions must already
+// be registered on the application or on `window`.
+pub fn apply(app: &gtk::Application, window: &gtk::ApplicationWindow, settings: &Settings) {
+    let defaults = GROUPS
+        .iter()
+        .flat_map(|(_, shortcuts)| shortcuts.iter())
+        .map(|s| s.action);
+    let remapped = settings.shortcuts.keys().map(String::as_str);
+
+    for action in defaults.chain(remapped) {
+        if gio::Action::parse_detailed_name(action).is_err() {
+            eprintln!("Ignoring shortcut for invalid action \"{}\"", action);
+            continue;
+        }
+        if !is_registered(app, window, action) {
+            eprintln!("Ignoring shortcut for unknown action \"{}\"", action);
+            continue;
+        }
+        let accels: Vec<String> = accels(settings, action)
+            .into_iter()
+            .filter(|accel| {
+                let valid = gtk::accelerator_parse(accel.as_str()).is_some();
+                if !valid {
+                    eprintln!("Ignoring invalid shortcut \"{}\" for {}", accel, action);
+                }
+                valid
+            })
+            .collect();
+        let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+        app.set_accels_for_action(action, &accels);
+    }
+}
+
+fn is_registered(app: &gtk::Application, window: &gtk::ApplicationWindow, action: &str) -> bool {
+    let Ok((name, _)) = gio::Action::parse_detailed_name(action) else {
+        return false;
+    };
+    if let Some(name) = name.strip_prefix("win.") {
+        window.lookup_action(name).is_some()
+    } else if let Some(name) = name.strip_prefix("app.") {
+        app.lookup_action(name).is_some()
+    } else {
+        false
+    }
+}
+
+// Help window listing the current bindings of the actions `window` provides
+pub fn window(app: &gtk::Application, window: &gtk::ApplicationWindow) -> gtk::ShortcutsWindow {
+    let escape = |text: &str| glib::markup_escape_text(text).to_string();
+    let mut groups = String::new();
+
+    for (title, shortcuts) in GROUPS {
+        let mut items = String::new();
+        for shortcut in *shortcuts {
+            let available = gio::Action::parse_detailed_name(shortcut.action)
+                .ok()
+                .and_then(|(name, _)| name.strip_prefix("win.").map(str::to_string))
+                .is_some_and(|name| window.lookup_action(&name).is_some());
+            let accels = app.accels_for_action(shortcut.action);
+            if !available || accels.is_empty() {
+                continue;
+            }
+            let accels: Vec<&str> = accels.iter().map(|a| a.as_str()).collect();
+            items.push_str(&format!(
+                "<child><object class=\"GtkShortcutsShortcut\">\
+                 <property name=\"title\">{}</property>\
+                 <property name=\"accelerator\">{}</property>\
+                 </object></child>",
+                escape(shortcut.title),
+                escape(&accels.join(" "))
+            ));
+        }
+        if !items.is_empty() {
+            groups.push_str(&format!(
+                "<child><object class=\"GtkShortcutsGroup\">\
+                 <property name=\"title\">{}</property>{}</object></child>",
+                escape(title),
+                items
+            ));
+        }
+    }
+
+    // Sections and groups can only be added through GtkBuilder before GTK 4.14
+    let ui = format!(
+        "<interface><object class=\"GtkShortcutsWindow\" id=\"shortcuts\">\
+         <property name=\"modal\">true</property>\
+         <child><object class=\"GtkShortcutsSection\">\
+         <property name=\"section-name\">shortcuts</property>\
+         <property name=\"max-height\">12</property>{}\
+         </object></child></object></interface>",
+        groups
+    );
+    let shortcuts: gtk::ShortcutsWindow = gtk::Builder::from_string(&ui)
+        .object("shortcuts")
+        .expect("shortcuts window");
+    shortcuts.set_transient_for(Some(window));
+    shortcuts
+}
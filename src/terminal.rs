@@ -0,0 +1,60 @@
+use crate::launcher;
+use gtk::glib;
+use gtk4 as gtk;
+use std::path::Path;
+
+// Emulators we know how to start in a given directory, in order of preference.
+// `%d` is replaced by the directory; every command also runs with it as cwd.
+const KNOWN_TERMINALS: &[(&str, &str)] = &[
+    ("kgx", "kgx --working-directory=%d"),
+    ("gnome-terminal", "gnome-terminal --working-directory=%d"),
+    ("konsole", "konsole --workdir %d"),
+    ("xfce4-terminal", "xfce4-terminal --working-directory=%d"),
+    ("tilix", "tilix --working-directory=%d"),
+    ("terminator", "terminator --working-directory=%d"),
+    ("alacritty", "alacritty --working-directory %d"),
+    ("kitty", "kitty --directory %d"),
+    ("wezterm", "wezterm start --cwd %d"),
+    ("foot", "foot --working-directory=%d"),
+    ("urxvt", "urxvt -cd %d"),
+    ("st", "st"),
+    ("xterm", "xterm"),
+];
+
+// Command template to use when the user has not configured one
+pub fn detect() -> Option<String> {
+    if let Some(terminal) = std::env::var("TERMINAL").ok().filter(|t| !t.is_empty()) {
+        return Some(template_for(&terminal));
+    }
+    if glib::find_program_in_path("x-terminal-emulator").is_some() {
+        return Some("x-terminal-emulator".to_string());
+    }
+    KNOWN_TERMINALS
+        .iter()
+        .find(|(program, _)| glib::find_program_in_path(program).is_some())
+        .map(|(_, template)| template.to_string())
+}
+
+// Known flags for a bare program name, or the program as-is
+fn template_for(program: &str) -> String {
+    let name = Path::new(program)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    KNOWN_TERMINALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(known, template)| template.replacen(known, program, 1))
+        .unwrap_or_else(|| program.to_string())
+}
+
+// Opens a terminal in `dir`; an empty template means auto-detect
+pub fn launch(template: &str, dir: &Path) -> Result<(), String> {
+    let template = if template.trim().is_empty() {
+        detect().ok_or_else(|| "No terminal emulator found, set one in Preferences".to_string())?
+    } else {
+        template.to_string()
+    };
+    let dir_str = dir.to_string_lossy();
+    launcher::spawn("terminal", &template, &[("%d", &*dir_str)], Some(dir))
+}
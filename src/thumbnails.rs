@@ -0,0 +1,450 @@
+// Thumbnails following the freedesktop Thumbnail Managing Standard: they are
+// shared with other file managers under ~/.cache/thumbnails, named by the
+// MD5 of the file's URI and valid while their Thumb::URI and Thumb::MTime
+// match the file. Files that could not be thumbnailed get an entry of the
+// same form under fail/<app>, so they are not tried again until they change.
+use crate::file_entry::FileEntry;
+use gtk::gdk;
+use gtk::gdk_pixbuf::Pixbuf;
+use gtk::gio;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk4 as gtk;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+// Edge of the "normal" size
+const SIZE: i32 = 128;
+const WORKERS: usize = 4;
+// Larger images are left to their icon
+const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024;
+// Loaded thumbnails kept in memory, so refreshing a view does not read them again
+const MAX_TEXTURES: usize = 2000;
+// Thumbnailers still running after this are killed
+const THUMBNAILER_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Requests dropped together, such as those of a view that was replaced
+#[derive(Clone, Default)]
+pub struct Batch(Arc<AtomicBool>);
+
+impl Batch {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// A file and modification time, in seconds
+type Key = (PathBuf, u64);
+
+struct Job {
+    id: u64,
+    path: PathBuf,
+    mtime: u64,
+    mime: String,
+    batch: Batch,
+}
+
+enum Outcome {
+    Thumbnail(gdk::Texture),
+    Failed,
+    Cancelled,
+}
+
+type Callback = Box<dyn FnOnce(&gdk::Texture)>;
+
+struct Pool {
+    jobs: mpsc::Sender<Job>,
+    results: mpsc::Receiver<(u64, Outcome)>,
+    pending: HashMap<u64, (Key, Callback)>,
+    textures: HashMap<Key, gdk::Texture>,
+    // Failures are not retried until the file changes or the app restarts
+    failed: HashSet<Key>,
+    // MIME types that GdkPixbuf or an installed thumbnailer can handle
+    supported: HashSet<String>,
+    next_id: u64,
+    polling: bool,
+}
+
+thread_local! {
+    static POOL: RefCell<Option<Pool>> = const { RefCell::new(None) };
+}
+
+// Calls `done` on the main loop with the thumbnail of `entry`, at once when
+// it is in memory, later when it has to be read or made, never when the
+// file cannot have one
+pub fn request<F: FnOnce(&gdk::Texture) + 'static>(entry: &FileEntry, batch: &Batch, done: F) {
+    if entry.is_dir || entry.path.starts_with(cache_dir()) {
+        return;
+    }
+    let mtime = entry
+        .modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let key = (entry.path.clone(), mtime);
+
+    let mut done = Some(done);
+    let texture = POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let pool = pool.get_or_insert_with(Pool::start);
+        if let Some(texture) = pool.textures.get(&key) {
+            return Some(texture.clone());
+        }
+        let mime = entry.mime_type();
+        if pool.failed.contains(&key) || !pool.supported.contains(&mime) {
+            return None;
+        }
+        if mime.starts_with("image/") && entry.size > MAX_IMAGE_BYTES {
+            return None;
+        }
+
+        let id = pool.next_id;
+        pool.next_id += 1;
+        let job = Job {
+            id,
+            path: entry.path.clone(),
+            mtime,
+            mime,
+            batch: batch.clone(),
+        };
+        if let (Ok(()), Some(done)) = (pool.jobs.send(job), done.take()) {
+            pool.pending.insert(id, (key, Box::new(done)));
+            pool.ensure_polling();
+        }
+        None
+    });
+    // The borrow has ended, so `done` may request again
+    if let (Some(texture), Some(done)) = (texture, done) {
+        done(&texture);
+    }
+}
+
+impl Pool {
+    fn start() -> Self {
+        let thumbnailers = Arc::new(load_thumbnailers());
+        let mut supported: HashSet<String> = thumbnailers.keys().cloned().collect();
+        for format in Pixbuf::formats() {
+            supported.extend(format.mime_types().iter().map(|m| m.to_string()));
+        }
+
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..WORKERS {
+            let queue = job_receiver.clone();
+            let results = result_sender.clone();
+            let thumbnailers = thumbnailers.clone();
+            std::thread::spawn(move || loop {
+                let job = match queue.lock().map(|queue| queue.recv()) {
+                    Ok(Ok(job)) => job,
+                    _ => return,
+                };
+                let outcome = if job.batch.is_cancelled() {
+                    Outcome::Cancelled
+                } else {
+                    match thumbnail(&job, &thumbnailers) {
+                        Some(texture) => Outcome::Thumbnail(texture),
+                        None => Outcome::Failed,
+                    }
+                };
+                if results.send((job.id, outcome)).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Self {
+            jobs,
+            results,
+            pending: HashMap::new(),
+            textures: HashMap::new(),
+            failed: HashSet::new(),
+            supported,
+            next_id: 0,
+            polling: false,
+        }
+    }
+
+    // Hands finished thumbnails to their callbacks until nothing is pending
+    fn ensure_polling(&mut self) {
+        if self.polling {
+            return;
+        }
+        self.polling = true;
+        glib::timeout_add_local(Duration::from_millis(50), || {
+            let (ready, more) = POOL.with(|pool| {
+                let mut pool = pool.borrow_mut();
+                let Some(pool) = pool.as_mut() else {
+                    return (Vec::new(), false);
+                };
+                let mut ready = Vec::new();
+                while let Ok((id, outcome)) = pool.results.try_recv() {
+                    let Some((key, done)) = pool.pending.remove(&id) else {
+                        continue;
+                    };
+                    match outcome {
+                        Outcome::Thumbnail(texture) => {
+                            if pool.textures.len() >= MAX_TEXTURES {
+                                pool.textures.clear();
+                            }
+                            pool.textures.insert(key, texture.clone());
+                            ready.push((done, texture));
+                        }
+                        Outcome::Failed => {
+                            pool.failed.insert(key);
+                        }
+                        Outcome::Cancelled => {}
+                    }
+                }
+                pool.polling = !pool.pending.is_empty();
+                (ready, pool.polling)
+            });
+            for (done, texture) in ready {
+                done(&texture);
+            }
+            if more {
+                glib::ControlFlow::Continue
+            } else {
+                glib::ControlFlow::Break
+            }
+        });
+    }
+}
+
+// Application name and version under fail/, as the standard asks
+const FAIL_DIR: &str = concat!("owl-", env!("CARGO_PKG_VERSION"));
+
+fn cache_dir() -> PathBuf {
+    glib::user_cache_dir().join("thumbnails")
+}
+
+// Reuses a current thumbnail made by anyone, otherwise makes one in "normal"
+fn thumbnail(job: &Job, thumbnailers: &HashMap<String, String>) -> Option<gdk::Texture> {
+    let uri = gio::File::for_path(&job.path).uri().to_string();
+    let hash = glib::compute_checksum_for_string(glib::ChecksumType::Md5, &uri)?;
+    let name = format!("{}.png", hash);
+
+    for size in ["normal", "large"] {
+        let existing = cache_dir().join(size).join(&name);
+        if is_current(&existing, &uri, job.mtime) {
+            if let Ok(texture) = gdk::Texture::from_filename(&existing) {
+                return Some(texture);
+            }
+        }
+    }
+    let failure = cache_dir().join("fail").join(FAIL_DIR).join(&name);
+    if is_current(&failure, &uri, job.mtime) {
+        return None;
+    }
+
+    let dir = cache_dir().join("normal");
+    fs::create_dir_all(&dir).ok()?;
+    let _ = fs::set_permissions(&dir, fs::Permissions::from_mode(0o700));
+    // Written under a temporary name and renamed, so other programs never
+    // see half a file
+    let temporary = dir.join(format!("owl-{}-{}.png", std::process::id(), job.id));
+    let made = match thumbnailers.get(&job.mime) {
+        Some(exec) => run_thumbnailer(exec, &job.path, &uri, &temporary),
+        None => Pixbuf::from_file(&job.path).ok(),
+    }
+    .and_then(|pixbuf| save(&pixbuf, &temporary, &uri, job.mtime));
+
+    let target = dir.join(&name);
+    let texture = made
+        .and_then(|_| fs::rename(&temporary, &target).ok())
+        .and_then(|_| gdk::Texture::from_filename(&target).ok());
+    let _ = fs::remove_file(&temporary);
+    if texture.is_none() {
+        record_failure(&failure, &uri, job.mtime);
+    }
+    texture
+}
+
+// A blank 1×1 PNG with the source's URI and MTime, as the standard's fail entries are
+fn record_failure(path: &Path, uri: &str, mtime: u64) {
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    if fs::create_dir_all(dir).is_err() {
+        return;
+    }
+    let _ = fs::set_permissions(dir, fs::Permissions::from_mode(0o700));
+    if let Some(pixbuf) = Pixbuf::new(gtk::gdk_pixbuf::Colorspace::Rgb, true, 8, 1, 1) {
+        pixbuf.fill(0);
+        let _ = save(&pixbuf, path, uri, mtime);
+    }
+}
+
+// Scales down to the thumbnail size and records the source as the standard asks
+fn save(pixbuf: &Pixbuf, path: &Path, uri: &str, mtime: u64) -> Option<()> {
+    let (width, height) = (pixbuf.width(), pixbuf.height());
+    let scale = (SIZE as f64 / width.max(height) as f64).min(1.0);
+    let scaled = pixbuf.scale_simple(
+        ((width as f64 * scale).round() as i32).max(1),
+        ((height as f64 * scale).round() as i32).max(1),
+        gtk::gdk_pixbuf::InterpType::Bilinear,
+    )?;
+    let mtime = mtime.to_string();
+    scaled
+        .savev(
+            path,
+            "png",
+            &[("tEXt::Thumb::URI", uri), ("tEXt::Thumb::MTime", &mtime)],
+        )
+        .ok()?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).ok()
+}
+
+// Runs the `Exec` line of a .thumbnailer file, writing to `output`
+fn run_thumbnailer(exec: &str, path: &Path, uri: &str, output: &Path) -> Option<Pixbuf> {
+    let argv = glib::shell_parse_argv(exec).ok()?;
+    let mut args = argv.iter().map(|arg| {
+        let mut expanded = String::new();
+        let mut chars = arg
+            .to_string_lossy()
+            .chars()
+            .collect::<Vec<_>>()
+            .into_iter();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('s') => expanded.push_str(&SIZE.to_string()),
+                Some('u') => expanded.push_str(uri),
+                Some('i') => expanded.push_str(&path.to_string_lossy()),
+                Some('o') => expanded.push_str(&output.to_string_lossy()),
+                Some('%') => expanded.push('%'),
+                Some(other) => {
+                    expanded.push('%');
+                    expanded.push(other);
+                }
+                None => expanded.push('%'),
+            }
+        }
+        expanded
+    });
+    let program = args.next()?;
+    let mut child = Command::new(program).args(args).spawn().ok()?;
+
+    // A hung thumbnailer would hold a worker forever
+    let deadline = Instant::now() + THUMBNAILER_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        return None;
+    }
+    Pixbuf::from_file(output).ok()
+}
+
+// Exec lines of the installed thumbnailers by MIME type; the user's
+// ~/.local/share/thumbnailers come first, then /usr/share/thumbnailers and
+// the other system data directories
+fn load_thumbnailers() -> HashMap<String, String> {
+    let mut dirs = vec![glib::user_data_dir()];
+    dirs.extend(glib::system_data_dirs());
+    if !dirs.iter().any(|d| d == Path::new("/usr/share")) {
+        dirs.push(PathBuf::from("/usr/share"));
+    }
+
+    let group = "Thumbnailer Entry";
+    let mut thumbnailers = HashMap::new();
+    for dir in dirs {
+        let Ok(files) = fs::read_dir(dir.join("thumbnailers")) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = files
+            .filter_map(|f| f.ok())
+            .map(|f| f.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "thumbnailer"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let file = glib::KeyFile::new();
+            if file
+                .load_from_file(&path, glib::KeyFileFlags::NONE)
+                .is_err()
+            {
+                continue;
+            }
+            let Ok(exec) = file.string(group, "Exec") else {
+                continue;
+            };
+            let program = match file.string(group, "TryExec") {
+                Ok(try_exec) => Some(try_exec.to_string()),
+                Err(_) => glib::shell_parse_argv(exec.as_str())
+                    .ok()
+                    .and_then(|argv| argv.first().map(|p| p.to_string_lossy().into_owned())),
+            };
+            if program.is_none_or(|p| glib::find_program_in_path(p).is_none()) {
+                continue;
+            }
+            let Ok(mime_types) = file.string_list(group, "MimeType") else {
+                continue;
+            };
+            for mime in mime_types.iter() {
+                thumbnailers
+                    .entry(mime.to_string())
+                    .or_insert_with(|| exec.to_string());
+            }
+        }
+    }
+    thumbnailers
+}
+
+// A thumbnail is current when it was made from the file as it is now
+fn is_current(thumbnail: &Path, uri: &str, mtime: u64) -> bool {
+    png_text(thumbnail).is_some_and(|text| {
+        text.get("Thumb::URI").is_some_and(|u| u == uri)
+            && text
+                .get("Thumb::MTime")
+                .is_some_and(|t| t.trim() == mtime.to_string())
+    })
+}
+
+// tEXt chunks of a PNG file by keyword
+fn png_text(path: &Path) -> Option<HashMap<String, String>> {
+    let data = fs::read(path).ok()?;
+    let mut rest = data.strip_prefix(b"\x89PNG\r\n\x1a\n")?;
+    let mut text = HashMap::new();
+    // Each chunk is a length, a type, the data and a CRC
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[..4].try_into().ok()?) as usize;
+        let kind = &rest[4..8];
+        let body = rest.get(8..8 + length)?;
+        if kind == b"IEND" {
+            break;
+        }
+        if kind == b"tEXt" {
+            if let Some(nul) = body.iter().position(|&b| b == 0) {
+                text.insert(
+                    String::from_utf8_lossy(&body[..nul]).into_owned(),
+                    String::from_utf8_lossy(&body[nul + 1..]).into_owned(),
+                );
+            }
+        }
+        rest = rest.get(12 + length..)?;
+    }
+    Some(text)
+}
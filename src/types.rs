@@ -1,23 +1,275 @@
-#[derive(Clone, PartialEq, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum SortBy {
     #[default]
     Name,
     Size,
     Type,
     Date,
+    Permissions,
+    Owner,
+    Group,
+    Created,
+    Accessed,
+    MimeType,
+    LinkTarget,
+    ItemCount,
+    Extension,
+    Location,
+}
+
+impl SortBy {
+    pub fn from_id(id: &str) -> Self {
+        Column::from_id(id).map(|c| c.sort_by()).unwrap_or_default()
+    }
 }
 
-#[derive(Clone, PartialEq, Default)]
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ViewMode {
     #[default]
     List,
     Grid,
     Compact,
+    // Miller columns, one column per selected folder
+    Columns,
+}
+
+impl ViewMode {
+    pub fn id(&self) -> &'static str {
+        match self {
+            ViewMode::List => "list",
+            ViewMode::Grid => "grid",
+            ViewMode::Compact => "compact",
+            ViewMode::Columns => "columns",
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Default)]
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum SortOrder {
     #[default]
     Ascending,
     Descending,
 }
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeUnits {
+    // 1 KB = 1024 B
+    #[default]
+    Binary,
+    // 1 kB = 1000 B
+    Decimal,
+}
+
+// How sizes and dates are rendered in the views
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayFormat {
+    // strftime-style, see g_date_time_format()
+    pub date_format: String,
+    pub size_units: SizeUnits,
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self {
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            size_units: SizeUnits::default(),
+        }
+    }
+}
+
+// How names are compared and whether folders are grouped first
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOptions {
+    pub natural: bool,
+    pub locale: bool,
+    pub ignore_punctuation: bool,
+    pub folders_first: bool,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        Self {
+            natural: true,
+            locale: true,
+            ignore_punctuation: false,
+            folders_first: true,
+        }
+    }
+}
+
+// Columns available in the detailed list view
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Column {
+    Name,
+    Size,
+    Type,
+    Date,
+    Permissions,
+    Owner,
+    Group,
+    Created,
+    Accessed,
+    MimeType,
+    LinkTarget,
+    ItemCount,
+    Extension,
+    // Parent folder, only shown for search results
+    Location,
+}
+
+impl Column {
+    pub const ALL: [Column; 14] = [
+        Column::Name,
+        Column::Location,
+        Column::Size,
+        Column::Type,
+        Column::Date,
+        Column::Permissions,
+        Column::Owner,
+        Column::Group,
+        Column::Created,
+        Column::Accessed,
+        Column::MimeType,
+        Column::LinkTarget,
+        Column::ItemCount,
+        Column::Extension,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Size => "size",
+            Column::Type => "type",
+            Column::Date => "date",
+            Column::Permissions => "permissions",
+            Column::Owner => "owner",
+            Column::Group => "group",
+            Column::Created => "created",
+            Column::Accessed => "accessed",
+            Column::MimeType => "mime-type",
+            Column::LinkTarget => "link-target",
+            Column::ItemCount => "item-count",
+            Column::Extension => "extension",
+            Column::Location => "location",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+
+    // Name is always shown and Location only while showing search results
+    pub fn is_toggleable(&self) -> bool {
+        !matches!(self, Column::Name | Column::Location)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Size => "Size",
+            Column::Type => "Type",
+            Column::Date => "Date",
+            Column::Permissions => "Permissions",
+            Column::Owner => "Owner",
+            Column::Group => "Group",
+            Column::Created => "Created",
+            Column::Accessed => "Accessed",
+            Column::MimeType => "MIME Type",
+            Column::LinkTarget => "Link Target",
+            Column::ItemCount => "Items",
+            Column::Extension => "Extension",
+            Column::Location => "Location",
+        }
+    }
+
+    pub fn default_width(&self) -> i32 {
+        match self {
+            Column::Name => 200,
+            Column::Size => 80,
+            Column::Type => 100,
+            Column::Date | Column::Created | Column::Accessed => 150,
+            Column::Permissions => 100,
+            Column::Owner | Column::Group => 80,
+            Column::MimeType => 160,
+            Column::LinkTarget => 200,
+            Column::ItemCount => 70,
+            Column::Extension => 70,
+            Column::Location => 250,
+        }
+    }
+
+    pub fn sort_by(&self) -> SortBy {
+        match self {
+            Column::Name => SortBy::Name,
+            Column::Size => SortBy::Size,
+            Column::Type => SortBy::Type,
+            Column::Date => SortBy::Date,
+            Column::Permissions => SortBy::Permissions,
+            Column::Owner => SortBy::Owner,
+            Column::Group => SortBy::Group,
+            Column::Created => SortBy::Created,
+            Column::Accessed => SortBy::Accessed,
+            Column::MimeType => SortBy::MimeType,
+            Column::LinkTarget => SortBy::LinkTarget,
+            Column::ItemCount => SortBy::ItemCount,
+            Column::Extension => SortBy::Extension,
+            Column::Location => SortBy::Location,
+        }
+    }
+}
+
+// Position, visibility and width of one column in the detailed list view
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ColumnState {
+    pub column: Column,
+    pub visible: bool,
+    pub width: i32,
+}
+
+impl ColumnState {
+    pub fn new(column: Column, visible: bool) -> Self {
+        Self {
+            column,
+            visible,
+            width: column.default_width(),
+        }
+    }
+
+    pub fn defaults() -> Vec<ColumnState> {
+        Column::ALL
+            .into_iter()
+            .map(|c| {
+                let visible =
+                    matches!(c, Column::Name | Column::Size | Column::Type | Column::Date);
+                ColumnState::new(c, visible)
+            })
+            .collect()
+    }
+
+    // Adds any column missing from a saved layout at the end, hidden
+    pub fn normalize(columns: &mut Vec<ColumnState>) {
+        let mut seen = Vec::new();
+        columns.retain(|c| {
+            let first = !seen.contains(&c.column);
+            seen.push(c.column);
+            first
+        });
+        for column in Column::ALL {
+            if !columns.iter().any(|c| c.column == column) {
+                columns.push(ColumnState::new(column, false));
+            }
+        }
+        for state in columns.iter_mut().filter(|c| c.column == Column::Name) {
+            state.visible = true;
+        }
+    }
+}
@@ -1,11 +1,25 @@
+use crate::app::OwlApplication;
 use crate::file_entry::FileEntry;
-use crate::types::{SortBy, SortOrder, ViewMode};
+use crate::search::{ContentHit, SearchResult};
+use crate::settings::{Settings, ViewSettings};
+use crate::thumbnails;
+use crate::types::{Column, ColumnState, DisplayFormat, SortBy, SortOptions, SortOrder, ViewMode};
+use gtk::gdk;
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk4 as gtk;
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const MIN_COLUMN_WIDTH: i32 = 40;
+// Pause after which type-ahead starts a new prefix
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
 
 mod imp {
     use super::*;
@@ -13,6 +27,10 @@ mod imp {
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(file = "../../data/content_panel.ui")]
     pub struct OwlContentPanel {
+        #[template_child]
+        pub filter_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub filter_entry: TemplateChild<gtk::SearchEntry>,
         #[template_child]
         pub column_header: TemplateChild<gtk::Box>,
         #[template_child]
@@ -22,15 +40,54 @@ mod imp {
         #[template_child]
         pub file_list: TemplateChild<gtk::ListBox>, // list with fields view
         #[template_child]
+        pub tree_list: TemplateChild<gtk::ListBox>, // list view with expandable folders
+        #[template_child]
         pub compact_list: TemplateChild<gtk::ListBox>, // compact list without fields view
         #[template_child]
         pub flow_box: TemplateChild<gtk::FlowBox>, // grid view
+        #[template_child]
+        pub columns_scroll: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub columns_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub column_list: TemplateChild<gtk::ListBox>, // first of the Miller columns
         pub sort_by: RefCell<SortBy>,
         pub sort_order: RefCell<SortOrder>,
+        pub sort_options: RefCell<SortOptions>,
         pub view_mode: RefCell<ViewMode>,
         pub entries: RefCell<Vec<FileEntry>>,
+        pub columns: RefCell<Vec<ColumnState>>,
+        pub column_actions: gio::SimpleActionGroup,
+        pub path: RefCell<PathBuf>,
+        // Navigation history of the tab showing this panel
+        pub history: RefCell<Vec<PathBuf>>,
+        pub forward_stack: RefCell<Vec<PathBuf>>,
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
 
         pub show_hidden_files: RefCell<bool>,
+        // The list view shows folders as expandable tree nodes
+        pub tree_view: RefCell<bool>,
+        // Model bound to `tree_list` while it is shown
+        pub tree_model: RefCell<Option<gtk::TreeListModel>>,
+        // Folders expanded in the tree, kept across refreshes of the same path
+        pub expanded: RefCell<HashSet<PathBuf>>,
+        // Entries are search results rather than the contents of `path`
+        pub searching: RefCell<bool>,
+        // Matching lines of content search results, by path
+        pub hits: RefCell<HashMap<PathBuf, Vec<ContentHit>>>,
+        // Quick filter text; rows that do not match are hidden, not removed
+        pub filter: RefCell<String>,
+        // Name labels of the populated view, in `entries` order, for highlighting
+        pub name_labels: RefCell<Vec<gtk::Label>>,
+        // Prefix typed so far and when the last key came in
+        pub type_ahead: RefCell<String>,
+        pub type_ahead_time: RefCell<Option<Instant>>,
+        // Miller columns to the right of `column_list`, with their entries;
+        // a trailing preview has no list
+        pub column_lists: RefCell<Vec<gtk::ListBox>>,
+        pub column_entries: RefCell<Vec<Vec<FileEntry>>>,
+        // Thumbnail requests of the populated view, dropped when it is replaced
+        pub thumbnails: RefCell<thumbnails::Batch>,
     }
 
     #[glib::object_subclass]
@@ -48,7 +105,21 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for OwlContentPanel {}
+    impl ObjectImpl for OwlContentPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let panel = self.obj();
+            *self.columns.borrow_mut() = ColumnState::defaults();
+            panel.setup_column_actions();
+            panel.setup_column_menu();
+            panel.setup_filter();
+            panel.setup_type_ahead();
+            panel.setup_quick_look();
+            panel.setup_columns();
+            panel.setup_tree();
+            panel.rebuild_column_header();
+        }
+    }
     impl WidgetImpl for OwlContentPanel {}
     impl BoxImpl for OwlContentPanel {}
 }
@@ -65,19 +136,90 @@ impl OwlContentPanel {
         glib::Object::new()
     }
 
-    pub fn load_directory(&self, path: &PathBuf) {
+    // Global options come from the settings, per-directory view state is applied on load
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
         let imp = self.imp();
-        let mut entries = FileEntry::list_directory(path);
-        if !*imp.show_hidden_files.borrow() {
-            entries.retain(|f| *imp.show_hidden_files.borrow() || !f.name.starts_with('.'));
+        {
+            let settings = settings.borrow();
+            self.apply_view(settings.view.clone());
         }
+        *imp.settings.borrow_mut() = Some(settings);
+        self.apply_settings();
+    }
 
-        self.sort_entries(&mut entries);
-        *imp.entries.borrow_mut() = entries;
+    // Re-read global options after the preferences changed
+    pub fn apply_settings(&self) {
+        let imp = self.imp();
+        let Some(settings) = imp.settings.borrow().clone() else {
+            return;
+        };
+        {
+            let settings = settings.borrow();
+            *imp.show_hidden_files.borrow_mut() = settings.show_hidden_files;
+            *imp.tree_view.borrow_mut() = settings.tree_view;
+            *imp.sort_options.borrow_mut() = settings.sort_options.clone();
+            imp.file_list
+                .set_activate_on_single_click(settings.single_click);
+            imp.tree_list
+                .set_activate_on_single_click(settings.single_click);
+            imp.compact_list
+                .set_activate_on_single_click(settings.single_click);
+            imp.flow_box
+                .set_activate_on_single_click(settings.single_click);
+            // Without type-ahead, typing anywhere in the panel opens the filter bar
+            let capture = (!settings.type_ahead).then_some(self.upcast_ref::<gtk::Widget>());
+            imp.filter_bar.set_key_capture_widget(capture);
+        }
+
+        let path = imp.path.borrow().clone();
+        if path != PathBuf::new() {
+            self.load_directory(&path);
+        }
+    }
+
+    pub fn display_format(&self) -> DisplayFormat {
+        self.imp()
+            .settings
+            .borrow()
+            .as_ref()
+            .map(|s| s.borrow().display.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn load_directory(&self, path: &PathBuf) {
+        let imp = self.imp();
+        if imp.searching.replace(false) {
+            imp.hits.borrow_mut().clear();
+            self.rebuild_column_header();
+        }
+        if *imp.path.borrow() != *path {
+            *imp.path.borrow_mut() = path.clone();
+            imp.filter_bar.set_search_mode(false);
+            imp.expanded.borrow_mut().clear();
+            let view = imp
+                .settings
+                .borrow()
+                .as_ref()
+                .map(|s| s.borrow().view_for(path));
+            if let Some(view) = view {
+                self.apply_view(view);
+            }
+        }
+        *imp.entries.borrow_mut() = self.list_entries(path);
         self.refresh_view();
         self.update_sort_headers();
     }
 
+    // Contents of `path` as shown: hidden files filtered out unless enabled, sorted
+    fn list_entries(&self, path: &Path) -> Vec<FileEntry> {
+        let mut entries = FileEntry::list_directory(path);
+        if !*self.imp().show_hidden_files.borrow() {
+            entries.retain(|f| !f.name.starts_with('.'));
+        }
+        self.sort_entries(&mut entries);
+        entries
+    }
+
     fn update_sort_headers(&self) {
         let imp = self.imp();
         let sort_by = imp.sort_by.borrow().clone();
@@ -88,13 +230,7 @@ impl OwlContentPanel {
             SortOrder::Descending => " ↓",
         };
 
-        let labels = ["Name", "Size", "Type", "Date"];
-        let active = match sort_by {
-            SortBy::Name => 0,
-            SortBy::Size => 1,
-            SortBy::Type => 2,
-            SortBy::Date => 3,
-        };
+        let visible: Vec<Column> = self.visible_columns().iter().map(|c| c.column).collect();
 
         let header = imp.column_header.get();
         let mut child_opt = header.first_child();
@@ -103,12 +239,17 @@ impl OwlContentPanel {
         while let Some(widget) = child_opt {
             let next = widget.next_sibling();
             if let Ok(btn) = widget.downcast::<gtk::Button>() {
-                if i < labels.len() {
-                    if i == active {
-                        btn.set_label(&format!("{}{}", labels[i], arrow));
+                if let Some(column) = visible.get(i) {
+                    let label = btn.child().and_downcast::<gtk::Label>();
+                    if column.sort_by() == sort_by {
+                        if let Some(label) = label {
+                            label.set_text(&format!("{}{}", column.label(), arrow));
+                        }
                         btn.add_css_class("accent");
                     } else {
-                        btn.set_label(labels[i]);
+                        if let Some(label) = label {
+                            label.set_text(column.label());
+                        }
                         btn.remove_css_class("accent");
                     }
                     i += 1;
@@ -118,9 +259,96 @@ impl OwlContentPanel {
         }
     }
 
+    // Search results replace the listing until the next directory load
+    pub fn begin_search(&self) {
+        let imp = self.imp();
+        *imp.searching.borrow_mut() = true;
+        imp.entries.borrow_mut().clear();
+        imp.hits.borrow_mut().clear();
+        self.rebuild_column_header();
+        self.refresh_view();
+    }
+
+    // Appends a batch of matches without rebuilding the rows already shown
+    pub fn append_search_results(&self, results: Vec<SearchResult>) {
+        let imp = self.imp();
+        let mut batch = Vec::with_capacity(results.len());
+        for result in results {
+            if !result.hits.is_empty() {
+                imp.hits
+                    .borrow_mut()
+                    .insert(result.entry.path.clone(), result.hits);
+            }
+            batch.push(result.entry);
+        }
+        self.sort_entries(&mut batch);
+
+        let format = self.display_format();
+        let mode = imp.view_mode.borrow().clone();
+        for entry in &batch {
+            match mode {
+                ViewMode::List => imp
+                    .file_list
+                    .append(&self.make_list_row(entry, &format, None)),
+                ViewMode::Grid => self.append_grid_item(entry),
+                ViewMode::Compact => imp.compact_list.append(&self.make_compact_row(entry)),
+                ViewMode::Columns => imp.column_list.append(&self.make_compact_row(entry)),
+            }
+        }
+        imp.entries.borrow_mut().extend(batch);
+    }
+
+    // Sorts the complete result set once the walk is over
+    pub fn finish_search(&self) {
+        let imp = self.imp();
+        let mut entries = imp.entries.borrow().clone();
+        self.sort_entries(&mut entries);
+        *imp.entries.borrow_mut() = entries;
+        self.refresh_view();
+    }
+
+    // Drops results whose files were moved or deleted since they were found
+    pub fn remove_missing_results(&self) {
+        let imp = self.imp();
+        let before = imp.entries.borrow().len();
+        imp.entries
+            .borrow_mut()
+            .retain(|e| e.path.symlink_metadata().is_ok());
+        if imp.entries.borrow().len() != before {
+            imp.hits
+                .borrow_mut()
+                .retain(|path, _| path.symlink_metadata().is_ok());
+            self.refresh_view();
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        *self.imp().searching.borrow()
+    }
+
+    pub fn show_hidden_files(&self) -> bool {
+        *self.imp().show_hidden_files.borrow()
+    }
+
     pub fn set_view_mode(&self, mode: ViewMode) {
         *self.imp().view_mode.borrow_mut() = mode;
         self.refresh_view();
+        self.store_view();
+    }
+
+    pub fn set_show_hidden_files(&self, show: bool) {
+        *self.imp().show_hidden_files.borrow_mut() = show;
+        self.update_settings(|s| s.show_hidden_files = show);
+    }
+
+    pub fn tree_view(&self) -> bool {
+        *self.imp().tree_view.borrow()
+    }
+
+    pub fn set_tree_view(&self, enabled: bool) {
+        *self.imp().tree_view.borrow_mut() = enabled;
+        self.update_settings(|s| s.tree_view = enabled);
+        self.refresh_view();
     }
 
     pub fn set_sort_menu(&self, sort_by: SortBy) {
@@ -129,6 +357,19 @@ impl OwlContentPanel {
         *imp.sort_by.borrow_mut() = sort_by;
         *imp.sort_order.borrow_mut() = SortOrder::Ascending;
 
+        let mut entries = imp.entries.borrow().clone();
+        self.sort_entries(&mut entries);
+        *imp.entries.borrow_mut() = entries;
+        self.refresh_view();
+        self.store_view();
+    }
+
+    pub fn set_sort_options(&self, options: SortOptions) {
+        let imp = self.imp();
+
+        *imp.sort_options.borrow_mut() = options.clone();
+        self.update_settings(|s| s.sort_options = options);
+
         let mut entries = imp.entries.borrow().clone();
         self.sort_entries(&mut entries);
         *imp.entries.borrow_mut() = entries;
@@ -154,6 +395,7 @@ impl OwlContentPanel {
         *imp.entries.borrow_mut() = entries;
         self.refresh_view();
         self.update_sort_headers();
+        self.store_view();
     }
 
     // Private methods
@@ -161,16 +403,41 @@ impl OwlContentPanel {
         let imp = self.imp();
         let sort_by = imp.sort_by.borrow().clone();
         let sort_order = imp.sort_order.borrow().clone();
+        let options = imp.sort_options.borrow().clone();
+
+        // Keys that are looked up, guessed or read from disk are worked out
+        // once per entry instead of in every comparison
+        let key = |e: &FileEntry| match sort_by {
+            SortBy::Type => SortKey::Text(e.kind_display()),
+            SortBy::Owner => SortKey::Text(e.owner_display()),
+            SortBy::Group => SortKey::Text(e.group_display()),
+            SortBy::MimeType => SortKey::Text(e.mime_type()),
+            SortBy::Extension => SortKey::Text(e.extension()),
+            SortBy::ItemCount => SortKey::Count(e.item_count()),
+            _ => SortKey::None,
+        };
+        let mut keyed: Vec<(SortKey, FileEntry)> =
+            entries.drain(..).map(|e| (key(&e), e)).collect();
 
-        entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
+        keyed.sort_by(|(ka, a), (kb, b)| match (a.is_dir, b.is_dir) {
+            (true, false) if options.folders_first => Ordering::Less,
+            (false, true) if options.folders_first => Ordering::Greater,
             _ => {
                 let ord = match sort_by {
-                    SortBy::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                    SortBy::Name => compare_names(&a.name, &b.name, &options),
                     SortBy::Size => a.size.cmp(&b.size),
-                    SortBy::Type => a.kind_display().cmp(&b.kind_display()),
                     SortBy::Date => a.modified.cmp(&b.modified),
+                    SortBy::Permissions => a.mode.cmp(&b.mode),
+                    SortBy::Created => a.created.cmp(&b.created),
+                    SortBy::Accessed => a.accessed.cmp(&b.accessed),
+                    SortBy::LinkTarget => a.link_target.cmp(&b.link_target),
+                    SortBy::Location => a.path.parent().cmp(&b.path.parent()),
+                    SortBy::Type
+                    | SortBy::Owner
+                    | SortBy::Group
+                    | SortBy::MimeType
+                    | SortBy::Extension
+                    | SortBy::ItemCount => ka.cmp(kb),
                 };
 
                 match sort_order {
@@ -179,6 +446,88 @@ impl OwlContentPanel {
                 }
             }
         });
+        entries.extend(keyed.into_iter().map(|(_, e)| e));
+    }
+
+    // Called whenever the selection of any view changes; read it back with
+    // `selected_entry` or `selected_entries`
+    pub fn connect_selection_changed<F: Fn() + 'static>(&self, f: F) {
+        let imp = self.imp();
+        let f = Rc::new(f);
+        for list_box in [
+            imp.file_list.get(),
+            imp.tree_list.get(),
+            imp.compact_list.get(),
+            imp.column_list.get(),
+        ] {
+            let f = f.clone();
+            list_box.connect_selected_rows_changed(move |_| f());
+        }
+        imp.flow_box.connect_selected_children_changed(move |_| f());
+    }
+
+    // Entry under the selection of the visible view, if any
+    pub fn selected_entry(&self) -> Option<FileEntry> {
+        self.entry_at(self.selected_index()?)
+    }
+
+    // Selects every entry of the visible view; the columns view keeps one
+    pub fn select_all(&self) {
+        match self.list_box() {
+            Some(list) => list.select_all(),
+            None => self.imp().flow_box.select_all(),
+        }
+    }
+
+    // Every selected entry of the visible view, in display order
+    pub fn selected_entries(&self) -> Vec<FileEntry> {
+        let imp = self.imp();
+        let mut indices: Vec<i32> = match self.list_box() {
+            Some(list) => list.selected_rows().iter().map(|r| r.index()).collect(),
+            None => imp
+                .flow_box
+                .selected_children()
+                .iter()
+                .map(|c| c.index())
+                .collect(),
+        };
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .filter_map(|i| usize::try_from(i).ok())
+            .filter_map(|i| self.entry_at(i))
+            .collect()
+    }
+
+    // Entry at `index` of the visible view; in the tree that counts the
+    // contents of expanded folders
+    pub fn entry_at(&self, index: usize) -> Option<FileEntry> {
+        let imp = self.imp();
+        match imp.tree_model.borrow().as_ref() {
+            Some(model) => model.row(index as u32).as_ref().and_then(tree_row_entry),
+            None => imp.entries.borrow().get(index).cloned(),
+        }
+    }
+
+    // Selects and scrolls to the entry for `path` if it is shown
+    pub fn select_path(&self, path: &Path) {
+        let index = self.shown_entries().iter().position(|e| e.path == path);
+        if let Some(index) = index {
+            self.select_index(Some(index));
+            self.focus_index(index);
+        }
+    }
+
+    // Entries of the visible view in display order
+    fn shown_entries(&self) -> Vec<FileEntry> {
+        let imp = self.imp();
+        match imp.tree_model.borrow().as_ref() {
+            Some(model) => (0..model.n_items())
+                .filter_map(|i| model.row(i))
+                .filter_map(|row| tree_row_entry(&row))
+                .collect(),
+            None => imp.entries.borrow().clone(),
+        }
     }
 
     pub fn set_visible_files(&self) {
@@ -198,11 +547,16 @@ impl OwlContentPanel {
         *imp.entries.borrow_mut() = entries;
         self.refresh_view();
         self.update_sort_headers();
+        self.store_view();
     }
 
     fn refresh_view(&self) {
         let imp = self.imp();
+        imp.thumbnails.replace(Default::default()).cancel();
         let mode = imp.view_mode.borrow().clone();
+        if !self.tree_active() {
+            self.unbind_tree();
+        }
 
         match mode {
             ViewMode::List => {
@@ -217,21 +571,197 @@ impl OwlContentPanel {
                 self.populate_compact_view();
                 imp.stack.set_visible_child_name("compact");
             }
+            ViewMode::Columns => {
+                self.populate_columns_view();
+                imp.stack.set_visible_child_name("columns");
+            }
+        }
+    }
+
+    // List box of the visible view; the grid has none
+    fn list_box(&self) -> Option<gtk::ListBox> {
+        let imp = self.imp();
+        match *imp.view_mode.borrow() {
+            ViewMode::List if self.tree_active() => Some(imp.tree_list.get()),
+            ViewMode::List => Some(imp.file_list.get()),
+            ViewMode::Compact => Some(imp.compact_list.get()),
+            ViewMode::Columns => Some(imp.column_list.get()),
+            ViewMode::Grid => None,
         }
     }
 
     // Detail list view
 
     fn populate_list_view(&self) {
-        let list_box = self.imp().file_list.get();
+        let imp = self.imp();
+        let list_box = imp.file_list.get();
         Self::clear_list_box(&list_box);
+        imp.name_labels.borrow_mut().clear();
 
-        for entry in self.imp().entries.borrow().iter() {
-            list_box.append(&self.make_list_row(entry));
+        let tree = self.tree_active();
+        list_box.set_visible(!tree);
+        imp.tree_list.set_visible(tree);
+        if tree {
+            self.populate_tree_view();
+            return;
         }
+
+        let format = self.display_format();
+        for entry in imp.entries.borrow().iter() {
+            list_box.append(&self.make_list_row(entry, &format, None));
+        }
+    }
+
+    // Search results are always listed flat
+    fn tree_active(&self) -> bool {
+        let imp = self.imp();
+        *imp.view_mode.borrow() == ViewMode::List
+            && *imp.tree_view.borrow()
+            && !*imp.searching.borrow()
     }
 
-    fn make_list_row(&self, entry: &FileEntry) -> gtk::ListBoxRow {
+    // Rebuilds the tree from `entries`, re-expanding the folders that were open.
+    // The quick filter applies to the top level only.
+    fn populate_tree_view(&self) {
+        let imp = self.imp();
+        let filter = imp.filter.borrow().clone();
+        let root = gio::ListStore::new::<glib::BoxedAnyObject>();
+        for entry in imp.entries.borrow().iter() {
+            if filter_positions(&entry.name, &filter).is_some() {
+                root.append(&glib::BoxedAnyObject::new(entry.clone()));
+            }
+        }
+
+        // Folders get an empty model that is filled on first expansion, so the
+        // disclosure triangles do not require reading every folder
+        let model = gtk::TreeListModel::new(root, false, false, |item| {
+            let item = item.downcast_ref::<glib::BoxedAnyObject>()?;
+            let is_dir = item.borrow::<FileEntry>().is_dir;
+            is_dir.then(|| gio::ListStore::new::<glib::BoxedAnyObject>().upcast())
+        });
+
+        let expanded = imp.expanded.borrow().clone();
+        let mut position = 0;
+        while let Some(row) = model.row(position) {
+            if tree_row_entry(&row).is_some_and(|e| expanded.contains(&e.path)) {
+                row.set_expanded(true);
+                self.fill_tree_row(&row);
+            }
+            position += 1;
+        }
+
+        let format = self.display_format();
+        imp.tree_list.bind_model(
+            Some(&model),
+            glib::clone!(
+                #[weak(rename_to = panel)]
+                self,
+                #[upgrade_or_else]
+                || gtk::ListBoxRow::new().upcast(),
+                move |item| {
+                    let row = item
+                        .downcast_ref::<gtk::TreeListRow>()
+                        .expect("tree list row");
+                    panel.make_tree_row(row, &format).upcast()
+                }
+            ),
+        );
+        *imp.tree_model.borrow_mut() = Some(model);
+    }
+
+    fn unbind_tree(&self) {
+        let imp = self.imp();
+        if imp.tree_model.take().is_some() {
+            imp.tree_list
+                .bind_model(None::<&gio::ListModel>, |_| gtk::ListBoxRow::new().upcast());
+        }
+    }
+
+    fn make_tree_row(&self, row: &gtk::TreeListRow, format: &DisplayFormat) -> gtk::ListBoxRow {
+        let entry = tree_row_entry(row).expect("tree row entry");
+        row.connect_expanded_notify(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move |row| {
+                let Some(entry) = tree_row_entry(row) else {
+                    return;
+                };
+                let mut expanded = panel.imp().expanded.borrow_mut();
+                if row.is_expanded() {
+                    expanded.insert(entry.path);
+                    drop(expanded);
+                    panel.fill_tree_row(row);
+                } else {
+                    expanded.remove(&entry.path);
+                }
+            }
+        ));
+        self.make_list_row(&entry, format, Some(row))
+    }
+
+    // Lists the folder's contents into its child model the first time it opens
+    fn fill_tree_row(&self, row: &gtk::TreeListRow) {
+        let Some(children) = row.children().and_downcast::<gio::ListStore>() else {
+            return;
+        };
+        if children.n_items() > 0 {
+            return;
+        }
+        let Some(entry) = tree_row_entry(row) else {
+            return;
+        };
+        let items: Vec<glib::BoxedAnyObject> = self
+            .list_entries(&entry.path)
+            .into_iter()
+            .map(glib::BoxedAnyObject::new)
+            .collect();
+        children.extend_from_slice(&items);
+    }
+
+    fn setup_tree(&self) {
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, _| panel.tree_key(keyval)
+        ));
+        self.imp().tree_list.add_controller(keys);
+    }
+
+    // Right opens the selected folder, Left closes it or moves to its parent
+    fn tree_key(&self, keyval: gdk::Key) -> glib::Propagation {
+        let model = self.imp().tree_model.borrow().clone();
+        let (Some(model), Some(index)) = (model, self.selected_index()) else {
+            return glib::Propagation::Proceed;
+        };
+        let Some(row) = model.row(index as u32) else {
+            return glib::Propagation::Proceed;
+        };
+        match keyval {
+            gdk::Key::Right if row.is_expandable() => row.set_expanded(true),
+            gdk::Key::Left if row.is_expanded() => row.set_expanded(false),
+            gdk::Key::Left => {
+                let Some(parent) = row.parent() else {
+                    return glib::Propagation::Proceed;
+                };
+                let position = parent.position() as usize;
+                self.select_index(Some(position));
+                self.focus_index(position);
+            }
+            _ => return glib::Propagation::Proceed,
+        }
+        glib::Propagation::Stop
+    }
+
+    // `tree_row` wraps the name cell in a disclosure triangle
+    fn make_list_row(
+        &self,
+        entry: &FileEntry,
+        format: &DisplayFormat,
+        tree_row: Option<&gtk::TreeListRow>,
+    ) -> gtk::ListBoxRow {
         let row = gtk::ListBoxRow::new();
         let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 0);
         hbox.set_margin_top(4);
@@ -239,43 +769,93 @@ impl OwlContentPanel {
         hbox.set_margin_start(6);
         hbox.set_margin_end(6);
 
-        // Icon
-        let icon = gtk::Image::from_icon_name(entry.icon_name());
-        icon.set_margin_end(8);
-        hbox.append(&icon);
+        // One cell per visible column so widths can be updated by index
+        for state in self.visible_columns() {
+            if state.column == Column::Name {
+                let cell = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+                cell.set_hexpand(true);
 
-        // Name
-        let name = gtk::Label::new(Some(&entry.name));
-        name.set_halign(gtk::Align::Start);
-        name.set_hexpand(true);
-        name.set_ellipsize(gtk::pango::EllipsizeMode::End);
-        hbox.append(&name);
+                // Icon
+                let icon = self.make_icon(entry);
+                icon.set_margin_end(8);
+                cell.append(&icon);
 
-        // Size
-        let size = gtk::Label::new(Some(&entry.size_display()));
-        size.set_halign(gtk::Align::Start);
-        size.set_width_request(80);
-        size.add_css_class("dim-label");
-        hbox.append(&size);
-
-        // Type
-        let kind = gtk::Label::new(Some(&entry.kind_display()));
-        kind.set_halign(gtk::Align::Start);
-        kind.set_width_request(100);
-        kind.add_css_class("dim-label");
-        hbox.append(&kind);
-
-        // Date
-        let date = gtk::Label::new(Some(&entry.date_display()));
-        date.set_halign(gtk::Align::Start);
-        date.set_width_request(150);
-        date.add_css_class("dim-label");
-        hbox.append(&date);
+                // Name
+                let name = gtk::Label::new(Some(&entry.name));
+                name.set_halign(gtk::Align::Start);
+                name.set_hexpand(true);
+                name.set_ellipsize(gtk::pango::EllipsizeMode::End);
+                cell.append(&name);
 
-        row.set_child(Some(&hbox));
+                match tree_row {
+                    Some(tree_row) => {
+                        self.highlight_name(&name, entry);
+                        let expander = gtk::TreeExpander::new();
+                        expander.set_list_row(Some(tree_row));
+                        expander.set_hexpand(true);
+                        expander.set_child(Some(&cell));
+                        hbox.append(&expander);
+                    }
+                    None => {
+                        self.register_name_label(&name, entry);
+                        hbox.append(&cell);
+                    }
+                }
+                continue;
+            }
+
+            let text = entry.column_display(state.column, format);
+            let label = gtk::Label::new(Some(&text));
+            label.set_xalign(0.0);
+            label.set_width_request(state.width);
+            // Keep the natural width small so the cell never grows past its column
+            label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            label.set_max_width_chars(1);
+            label.set_tooltip_text(Some(&text));
+            label.add_css_class("dim-label");
+            hbox.append(&label);
+        }
+
+        // Cells first, then the matching lines of a content search
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        vbox.append(&hbox);
+        if let Some(hits) = self.imp().hits.borrow().get(&entry.path) {
+            for hit in hits {
+                vbox.append(&self.make_hit_label(&entry.path, hit));
+            }
+        }
+
+        row.set_child(Some(&vbox));
         row
     }
 
+    // Clicking a matching line opens the file at that line
+    fn make_hit_label(&self, path: &Path, hit: &ContentHit) -> gtk::Label {
+        let label = gtk::Label::new(None);
+        label.set_markup(&format!(
+            "<span alpha=\"60%\">{}:</span> {}",
+            hit.line,
+            hit.markup()
+        ));
+        label.set_xalign(0.0);
+        label.set_margin_start(36);
+        label.set_margin_end(6);
+        label.set_margin_bottom(2);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        label.set_tooltip_text(Some("Open at this line"));
+        label.set_cursor_from_name(Some("pointer"));
+
+        let target = (path.to_string_lossy().to_string(), hit.line).to_variant();
+        let click = gtk::GestureClick::new();
+        click.connect_released(move |gesture, _, _, _| {
+            if let Some(widget) = gesture.widget() {
+                let _ = widget.activate_action("win.open-at-line", Some(&target));
+            }
+        });
+        label.add_controller(click);
+        label
+    }
+
     // Grid view
 
     fn populate_grid_view(&self) {
@@ -284,16 +864,21 @@ impl OwlContentPanel {
         while let Some(child) = flow_box.first_child() {
             flow_box.remove(&child);
         }
+        self.imp().name_labels.borrow_mut().clear();
 
         for entry in self.imp().entries.borrow().iter() {
-            let item = self.make_grid_item(entry);
-            flow_box.insert(&item, -1);
+            self.append_grid_item(entry);
+        }
+    }
 
-            // Ajustar el FlowBoxChild wrapper que GTK crea automáticamente
-            if let Some(child) = item.parent() {
-                child.set_valign(gtk::Align::Start);
-                child.set_vexpand(false);
-            }
+    fn append_grid_item(&self, entry: &FileEntry) {
+        let item = self.make_grid_item(entry);
+        self.imp().flow_box.insert(&item, -1);
+
+        // Ajustar el FlowBoxChild wrapper que GTK crea automáticamente
+        if let Some(child) = item.parent() {
+            child.set_valign(gtk::Align::Start);
+            child.set_vexpand(false);
         }
     }
 
@@ -308,7 +893,7 @@ impl OwlContentPanel {
         vbox.set_margin_top(4);
         vbox.set_margin_bottom(4);
 
-        let icon = gtk::Image::from_icon_name(entry.icon_name());
+        let icon = self.make_icon(entry);
         icon.set_pixel_size(48);
         icon.set_halign(gtk::Align::Center);
         icon.set_valign(gtk::Align::Center);
@@ -321,18 +906,44 @@ impl OwlContentPanel {
         name.set_wrap(true);
         name.set_lines(2);
         name.set_justify(gtk::Justification::Center);
+        self.register_name_label(&name, entry);
 
         vbox.append(&icon);
         vbox.append(&name);
+        vbox.set_tooltip_markup(self.hits_tooltip(entry).as_deref());
         vbox
     }
 
+    // Themed icon of `entry`, replaced by its thumbnail once there is one
+    fn make_icon(&self, entry: &FileEntry) -> gtk::Image {
+        let icon = gtk::Image::from_icon_name(entry.icon_name());
+        let enabled = self
+            .imp()
+            .settings
+            .borrow()
+            .as_ref()
+            .is_some_and(|s| s.borrow().show_thumbnails);
+        if enabled {
+            thumbnails::request(
+                entry,
+                &self.imp().thumbnails.borrow(),
+                glib::clone!(
+                    #[weak]
+                    icon,
+                    move |texture| icon.set_paintable(Some(texture))
+                ),
+            );
+        }
+        icon
+    }
+
     // Compact view
 
     fn populate_compact_view(&self) {
         let compact_list = self.imp().compact_list.get();
 
         Self::clear_list_box(&compact_list);
+        self.imp().name_labels.borrow_mut().clear();
         for entry in self.imp().entries.borrow().iter() {
             compact_list.append(&self.make_compact_row(entry));
         }
@@ -345,17 +956,978 @@ impl OwlContentPanel {
         hbox.set_margin_bottom(2);
         hbox.set_margin_start(6);
 
-        let icon = gtk::Image::from_icon_name(entry.icon_name());
+        let icon = self.make_icon(entry);
         let name = gtk::Label::new(Some(&entry.name));
         name.set_halign(gtk::Align::Start);
         name.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        self.register_name_label(&name, entry);
 
         hbox.append(&icon);
         hbox.append(&name);
+        hbox.set_tooltip_markup(self.hits_tooltip(entry).as_deref());
         row.set_child(Some(&hbox));
         row
     }
 
+    // Miller columns
+
+    fn setup_columns(&self) {
+        let imp = self.imp();
+        imp.column_list.set_filter_func(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            true,
+            move |row| panel.index_matches_filter(row.index())
+        ));
+        imp.column_list.connect_row_selected(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move |_, row| {
+                let entry = row.and_then(|r| {
+                    let index = usize::try_from(r.index()).ok()?;
+                    panel.imp().entries.borrow().get(index).cloned()
+                });
+                panel.column_selected(0, entry);
+            }
+        ));
+        self.connect_column_keys(&imp.column_list, 0);
+    }
+
+    fn populate_columns_view(&self) {
+        let column_list = self.imp().column_list.get();
+        Self::clear_list_box(&column_list);
+        self.imp().name_labels.borrow_mut().clear();
+        self.truncate_columns(0);
+        for entry in self.imp().entries.borrow().iter() {
+            column_list.append(&self.make_compact_row(entry));
+        }
+    }
+
+    // Keeps the first `count` columns after `column_list`
+    fn truncate_columns(&self, count: usize) {
+        let imp = self.imp();
+        imp.column_lists.borrow_mut().truncate(count);
+        imp.column_entries.borrow_mut().truncate(count);
+        let mut child = imp.columns_box.first_child();
+        let mut index = 0;
+        while let Some(widget) = child {
+            child = widget.next_sibling();
+            // The first child holds `column_list`
+            if index > count {
+                imp.columns_box.remove(&widget);
+            }
+            index += 1;
+        }
+    }
+
+    // A selection in column `depth` (0 is `column_list`) replaces the columns
+    // to its right with the folder's contents, or a preview of the file
+    fn column_selected(&self, depth: usize, entry: Option<FileEntry>) {
+        self.truncate_columns(depth);
+        let Some(entry) = entry else {
+            return;
+        };
+        let imp = self.imp();
+
+        let content: gtk::Widget = if entry.is_dir {
+            let entries = self.list_entries(&entry.path);
+            let list = gtk::ListBox::new();
+            list.set_selection_mode(gtk::SelectionMode::Browse);
+            list.set_activate_on_single_click(false);
+            for child in &entries {
+                list.append(&self.make_column_row(child));
+            }
+
+            let column = depth + 1;
+            list.connect_row_selected(glib::clone!(
+                #[weak(rename_to = panel)]
+                self,
+                move |_, row| {
+                    let entry = row.and_then(|r| panel.column_entry(column, r.index()));
+                    panel.column_selected(column, entry);
+                }
+            ));
+            // Opening a folder from a deeper column shows it as the panel's folder
+            list.connect_row_activated(glib::clone!(
+                #[weak(rename_to = panel)]
+                self,
+                move |_, row| {
+                    if let Some(entry) = panel.column_entry(column, row.index()) {
+                        if entry.is_dir {
+                            let path = entry.path.to_string_lossy().to_string();
+                            let _ = panel.activate_action("win.navigate", Some(&path.to_variant()));
+                        }
+                    }
+                }
+            ));
+            self.connect_column_keys(&list, column);
+
+            imp.column_lists.borrow_mut().push(list.clone());
+            imp.column_entries.borrow_mut().push(entries);
+            list.upcast()
+        } else {
+            self.make_preview(&entry)
+        };
+
+        let scroller = gtk::ScrolledWindow::new();
+        scroller.set_width_request(220);
+        scroller.set_has_frame(true);
+        scroller.set_hscrollbar_policy(gtk::PolicyType::Never);
+        scroller.set_child(Some(&content));
+        imp.columns_box.append(&scroller);
+        self.scroll_to_column(&scroller);
+    }
+
+    fn column_entry(&self, column: usize, index: i32) -> Option<FileEntry> {
+        let entries = self.imp().column_entries.borrow();
+        entries
+            .get(column.checked_sub(1)?)?
+            .get(usize::try_from(index).ok()?)
+            .cloned()
+    }
+
+    // Rows of the deeper columns; unlike `make_compact_row` they are not
+    // tracked for the quick filter, which only applies to the first column
+    fn make_column_row(&self, entry: &FileEntry) -> gtk::ListBoxRow {
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        hbox.set_margin_top(2);
+        hbox.set_margin_bottom(2);
+        hbox.set_margin_start(6);
+        hbox.set_margin_end(6);
+
+        let name = gtk::Label::new(Some(&entry.name));
+        name.set_halign(gtk::Align::Start);
+        name.set_hexpand(true);
+        name.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        hbox.append(&self.make_icon(entry));
+        hbox.append(&name);
+        if entry.is_dir {
+            hbox.append(&gtk::Image::from_icon_name("go-next-symbolic"));
+        }
+
+        let row = gtk::ListBoxRow::new();
+        row.set_child(Some(&hbox));
+        row
+    }
+
+    // Last column when a file is selected: large icon and its details
+    fn make_preview(&self, entry: &FileEntry) -> gtk::Widget {
+        let format = self.display_format();
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        vbox.set_margin_top(18);
+        vbox.set_margin_bottom(18);
+        vbox.set_margin_start(12);
+        vbox.set_margin_end(12);
+
+        let icon = self.make_icon(entry);
+        icon.set_pixel_size(96);
+        vbox.append(&icon);
+
+        let name = gtk::Label::new(Some(&entry.name));
+        name.set_wrap(true);
+        name.set_wrap_mode(gtk::pango::WrapMode::WordChar);
+        name.set_justify(gtk::Justification::Center);
+        name.add_css_class("heading");
+        vbox.append(&name);
+
+        for column in [
+            Column::Type,
+            Column::Size,
+            Column::Date,
+            Column::Permissions,
+        ] {
+            let label = gtk::Label::new(Some(&format!(
+                "{}: {}",
+                column.label(),
+                entry.column_display(column, &format)
+            )));
+            label.set_wrap(true);
+            label.add_css_class("dim-label");
+            vbox.append(&label);
+        }
+        vbox.upcast()
+    }
+
+    // Left and Right move the focus between columns
+    fn connect_column_keys(&self, list: &gtk::ListBox, column: usize) {
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, _| {
+                let target = match keyval {
+                    gdk::Key::Right => column + 1,
+                    gdk::Key::Left if column > 0 => column - 1,
+                    _ => return glib::Propagation::Proceed,
+                };
+                panel.focus_column(target);
+                glib::Propagation::Stop
+            }
+        ));
+        list.add_controller(keys);
+    }
+
+    fn focus_column(&self, column: usize) {
+        let imp = self.imp();
+        let list = match column {
+            0 => Some(imp.column_list.get()),
+            n => imp.column_lists.borrow().get(n - 1).cloned(),
+        };
+        let Some(list) = list else {
+            return;
+        };
+        let row = list.selected_row().or_else(|| list.row_at_index(0));
+        if let Some(row) = row {
+            list.select_row(Some(&row));
+            row.grab_focus();
+        }
+        if let Some(scroller) = list.ancestor(gtk::ScrolledWindow::static_type()) {
+            self.scroll_to_column(&scroller);
+        }
+    }
+
+    // Scrolls horizontally so the whole column is visible, once it has a size
+    fn scroll_to_column(&self, column: &impl IsA<gtk::Widget>) {
+        let column = column.clone().upcast::<gtk::Widget>();
+        glib::idle_add_local_once(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move || {
+                let imp = panel.imp();
+                // compute_point needs GTK 4.12
+                let Some((x, _)) = column.translate_coordinates(&*imp.columns_box, 0.0, 0.0) else {
+                    return;
+                };
+                imp.columns_scroll
+                    .hadjustment()
+                    .clamp_page(x, x + column.width() as f64);
+            }
+        ));
+    }
+
+    // Matching lines for the views that have no room to show them inline
+    fn hits_tooltip(&self, entry: &FileEntry) -> Option<String> {
+        let hits = self.imp().hits.borrow();
+        let lines: Vec<String> = hits
+            .get(&entry.path)?
+            .iter()
+            .map(|hit| format!("{}: {}", hit.line, hit.markup()))
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    // Quick filter
+
+    fn setup_filter(&self) {
+        let imp = self.imp();
+
+        imp.filter_bar.connect_entry(&*imp.filter_entry);
+
+        imp.file_list.set_filter_func(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            true,
+            move |row| panel.index_matches_filter(row.index())
+        ));
+        imp.compact_list.set_filter_func(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            true,
+            move |row| panel.index_matches_filter(row.index())
+        ));
+        imp.flow_box.set_filter_func(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            true,
+            move |child| panel.index_matches_filter(child.index())
+        ));
+
+        imp.filter_entry.connect_search_changed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move |entry| panel.set_filter(entry.text().trim())
+        ));
+        // Enter opens the first match
+        imp.filter_entry.connect_activate(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move |_| {
+                match panel.list_box() {
+                    Some(list) => list.selected_row().map(|r| r.activate()),
+                    None => panel
+                        .imp()
+                        .flow_box
+                        .selected_children()
+                        .first()
+                        .map(|c| c.activate()),
+                };
+            }
+        ));
+        // Closing the bar (Escape) brings every entry back
+        imp.filter_bar
+            .connect_search_mode_enabled_notify(glib::clone!(
+                #[weak(rename_to = panel)]
+                self,
+                move |bar| {
+                    if !bar.is_search_mode() {
+                        panel.imp().filter_entry.set_text("");
+                        panel.set_filter("");
+                    }
+                }
+            ));
+    }
+
+    pub fn toggle_filter(&self) {
+        let bar = &self.imp().filter_bar;
+        let active = !bar.is_search_mode();
+        bar.set_search_mode(active);
+        if active {
+            self.imp().filter_entry.grab_focus();
+        }
+    }
+
+    fn set_filter(&self, text: &str) {
+        let imp = self.imp();
+        if *imp.filter.borrow() == text {
+            return;
+        }
+        *imp.filter.borrow_mut() = text.to_string();
+
+        imp.file_list.invalidate_filter();
+        imp.compact_list.invalidate_filter();
+        imp.column_list.invalidate_filter();
+        imp.flow_box.invalidate_filter();
+        // The bound tree model cannot use a filter function
+        if self.tree_active() {
+            self.populate_tree_view();
+        }
+
+        for (label, entry) in imp
+            .name_labels
+            .borrow()
+            .iter()
+            .zip(imp.entries.borrow().iter())
+        {
+            self.highlight_name(label, entry);
+        }
+        let first = self
+            .shown_entries()
+            .iter()
+            .position(|e| filter_positions(&e.name, text).is_some());
+        self.select_index(first);
+    }
+
+    fn index_matches_filter(&self, index: i32) -> bool {
+        let imp = self.imp();
+        let filter = imp.filter.borrow();
+        filter.is_empty()
+            || imp
+                .entries
+                .borrow()
+                .get(index as usize)
+                .is_some_and(|e| filter_positions(&e.name, &filter).is_some())
+    }
+
+    fn register_name_label(&self, label: &gtk::Label, entry: &FileEntry) {
+        self.highlight_name(label, entry);
+        self.imp().name_labels.borrow_mut().push(label.clone());
+    }
+
+    // Bold the characters matched by the quick filter
+    fn highlight_name(&self, label: &gtk::Label, entry: &FileEntry) {
+        let filter = self.imp().filter.borrow();
+        match filter_positions(&entry.name, &filter).filter(|p| !p.is_empty()) {
+            Some(positions) => label.set_markup(&highlight_markup(&entry.name, &positions)),
+            None => label.set_text(&entry.name),
+        }
+    }
+
+    fn select_index(&self, index: Option<usize>) {
+        let imp = self.imp();
+        let Some(index) = index else {
+            return;
+        };
+        // The views allow several selected entries; this replaces them
+        match self.list_box() {
+            Some(list) => {
+                if let Some(row) = list.row_at_index(index as i32) {
+                    list.unselect_all();
+                    list.select_row(Some(&row));
+                }
+            }
+            None => {
+                if let Some(child) = imp.flow_box.child_at_index(index as i32) {
+                    imp.flow_box.unselect_all();
+                    imp.flow_box.select_child(&child);
+                }
+            }
+        }
+    }
+
+    // Type-ahead
+
+    fn setup_type_ahead(&self) {
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, state| {
+                let modifiers = gdk::ModifierType::CONTROL_MASK
+                    | gdk::ModifierType::ALT_MASK
+                    | gdk::ModifierType::SUPER_MASK;
+                match keyval.to_unicode() {
+                    Some(c) if !c.is_control() && !state.intersects(modifiers) => {
+                        panel.type_ahead(c)
+                    }
+                    _ => glib::Propagation::Proceed,
+                }
+            }
+        ));
+        self.add_controller(keys);
+    }
+
+    // Space opens the quick look window, unless it continues a type-ahead
+    // prefix or goes to an entry. Runs before the lists, which would toggle
+    // the row.
+    fn setup_quick_look(&self) {
+        let keys = gtk::EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, state| {
+                let imp = panel.imp();
+                let modifiers = gdk::ModifierType::CONTROL_MASK
+                    | gdk::ModifierType::ALT_MASK
+                    | gdk::ModifierType::SUPER_MASK
+                    | gdk::ModifierType::SHIFT_MASK;
+                if keyval != gdk::Key::space || state.intersects(modifiers) {
+                    return glib::Propagation::Proceed;
+                }
+                let typing = panel
+                    .root()
+                    .and_then(|r| r.focus())
+                    .is_some_and(|f| f.is::<gtk::Editable>());
+                let type_ahead = !imp.type_ahead.borrow().is_empty()
+                    && imp
+                        .type_ahead_time
+                        .borrow()
+                        .is_some_and(|t| t.elapsed() <= TYPE_AHEAD_TIMEOUT);
+                if typing || type_ahead || panel.selected_entry().is_none() {
+                    return glib::Propagation::Proceed;
+                }
+                match panel.activate_action("win.quick-look", None) {
+                    Ok(()) => glib::Propagation::Stop,
+                    Err(_) => glib::Propagation::Proceed,
+                }
+            }
+        ));
+        self.add_controller(keys);
+    }
+
+    // Jumps to the next entry, in display order, whose name starts with the
+    // typed prefix; typing the same letter again cycles through its entries
+    fn type_ahead(&self, c: char) -> glib::Propagation {
+        let imp = self.imp();
+        let enabled = imp
+            .settings
+            .borrow()
+            .as_ref()
+            .is_some_and(|s| s.borrow().type_ahead);
+        if !enabled || *imp.searching.borrow() || imp.filter_bar.is_search_mode() {
+            return glib::Propagation::Proceed;
+        }
+        // Among Miller columns only the first one is searched
+        if *imp.view_mode.borrow() == ViewMode::Columns
+            && !self
+                .root()
+                .and_then(|r| r.focus())
+                .is_some_and(|f| f.is_ancestor(&*imp.column_list))
+        {
+            return glib::Propagation::Proceed;
+        }
+
+        let now = Instant::now();
+        let expired = imp
+            .type_ahead_time
+            .replace(Some(now))
+            .is_none_or(|t| now.duration_since(t) > TYPE_AHEAD_TIMEOUT);
+        let mut prefix = imp.type_ahead.borrow_mut();
+        if expired {
+            prefix.clear();
+        }
+        // A space only counts inside a prefix, otherwise it keeps toggling rows
+        if c == ' ' && prefix.is_empty() {
+            return glib::Propagation::Proceed;
+        }
+        prefix.extend(c.to_lowercase());
+
+        let first = prefix.chars().next().unwrap_or(c);
+        let cycling = prefix.chars().all(|p| p == first);
+        let search = if cycling {
+            first.to_string()
+        } else {
+            prefix.clone()
+        };
+        drop(prefix);
+
+        let entries = self.shown_entries();
+        if entries.is_empty() {
+            return glib::Propagation::Stop;
+        }
+        // A longer prefix may still match the current entry, a repeated letter moves on
+        let selected = self.selected_index();
+        let start = match selected {
+            Some(i) if cycling => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        let found = (0..entries.len())
+            .map(|offset| (start + offset) % entries.len())
+            .find(|&i| entries[i].name.to_lowercase().starts_with(&search));
+
+        if let Some(index) = found.filter(|i| Some(*i) != selected) {
+            self.select_index(Some(index));
+            self.focus_index(index);
+        }
+        glib::Propagation::Stop
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let imp = self.imp();
+        let index = match self.list_box() {
+            Some(list) => list
+                .selected_row()
+                .or_else(|| list.selected_rows().into_iter().next())
+                .map(|r| r.index()),
+            None => imp.flow_box.selected_children().first().map(|c| c.index()),
+        };
+        index.and_then(|i| usize::try_from(i).ok())
+    }
+
+    // Moves keyboard focus to the entry so the view scrolls to it
+    fn focus_index(&self, index: usize) {
+        let imp = self.imp();
+        let widget: Option<gtk::Widget> = match self.list_box() {
+            Some(list) => list.row_at_index(index as i32).map(|r| r.upcast()),
+            None => imp
+                .flow_box
+                .child_at_index(index as i32)
+                .map(|c| c.upcast()),
+        };
+        if let Some(widget) = widget {
+            widget.grab_focus();
+        }
+    }
+
+    // Columns
+
+    fn rebuild_column_header(&self) {
+        let header = self.imp().column_header.get();
+        while let Some(child) = header.first_child() {
+            header.remove(&child);
+        }
+
+        // Resize handles sit on the edge facing the expanding Name column
+        let mut after_name = false;
+        for state in self.visible_columns() {
+            if state.column == Column::Name {
+                header.append(&self.make_column_button(&state));
+                after_name = true;
+                continue;
+            }
+            let btn = self.make_column_button(&state);
+            if after_name {
+                header.append(&self.make_resize_handle(&btn, state.column, true));
+                header.append(&btn);
+            } else {
+                header.append(&btn);
+                header.append(&self.make_resize_handle(&btn, state.column, false));
+            }
+        }
+        self.update_sort_headers();
+    }
+
+    // Columns shown in the list view, in order; Location only appears for search results
+    fn visible_columns(&self) -> Vec<ColumnState> {
+        let searching = *self.imp().searching.borrow();
+        self.imp()
+            .columns
+            .borrow()
+            .iter()
+            .filter(|c| match c.column {
+                Column::Location => searching,
+                _ => c.visible,
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn make_column_button(&self, state: &ColumnState) -> gtk::Button {
+        let column = state.column;
+        let label = gtk::Label::new(Some(column.label()));
+        label.set_xalign(0.0);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        label.set_max_width_chars(1);
+
+        let btn = gtk::Button::new();
+        btn.set_child(Some(&label));
+        btn.add_css_class("flat");
+        if column == Column::Name {
+            btn.set_hexpand(true);
+        } else {
+            btn.set_halign(gtk::Align::Start);
+            btn.set_width_request(state.width);
+        }
+        btn.set_action_name(Some("win.sort-column"));
+        btn.set_action_target(Some(column.id()));
+
+        // Drag a header onto another one to move it in front of it
+        let source = gtk::DragSource::new();
+        source.set_actions(gdk::DragAction::MOVE);
+        source.set_content(Some(&gdk::ContentProvider::for_value(
+            &column.id().to_value(),
+        )));
+        btn.add_controller(source);
+
+        let target = gtk::DropTarget::new(glib::Type::STRING, gdk::DragAction::MOVE);
+        target.connect_drop(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Some(dragged) = value
+                    .get::<String>()
+                    .ok()
+                    .and_then(|id| Column::from_id(&id))
+                else {
+                    return false;
+                };
+                panel.move_column(dragged, column);
+                true
+            }
+        ));
+        btn.add_controller(target);
+
+        btn
+    }
+
+    // Drag to resize the column, double-click to fit it to its contents
+    fn make_resize_handle(&self, btn: &gtk::Button, column: Column, leading: bool) -> gtk::Box {
+        let handle = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        handle.set_width_request(6);
+        handle.set_cursor_from_name(Some("col-resize"));
+        let line = gtk::Separator::new(gtk::Orientation::Vertical);
+        line.set_halign(gtk::Align::Center);
+        line.set_hexpand(true);
+        handle.append(&line);
+
+        // Offsets are relative to the handle, which moves while the column resizes,
+        // so track its origin in header coordinates to get the real pointer delta
+        let drag = gtk::GestureDrag::new();
+        let start = std::rc::Rc::new(std::cell::Cell::new((0, 0.0)));
+        drag.connect_drag_begin(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[weak]
+            btn,
+            #[strong]
+            start,
+            move |gesture, _, _| {
+                let origin = panel.handle_origin(gesture);
+                start.set((btn.width_request(), origin));
+            }
+        ));
+        drag.connect_drag_update(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[weak]
+            btn,
+            #[strong]
+            start,
+            move |gesture, dx, _| {
+                let (start_width, start_origin) = start.get();
+                let dx = dx + panel.handle_origin(gesture) - start_origin;
+                let dx = if leading { -dx } else { dx };
+                let width = (start_width + dx as i32).max(MIN_COLUMN_WIDTH);
+                btn.set_width_request(width);
+                panel.set_column_width(column, width, false);
+            }
+        ));
+        drag.connect_drag_end(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            move |_, _, _| panel.store_view()
+        ));
+        handle.add_controller(drag);
+
+        let click = gtk::GestureClick::new();
+        click.connect_pressed(glib::clone!(
+            #[weak(rename_to = panel)]
+            self,
+            #[weak]
+            btn,
+            move |_, n_press, _, _| {
+                if n_press == 2 {
+                    let width = panel.fit_column_width(column);
+                    btn.set_width_request(width);
+                    panel.set_column_width(column, width, true);
+                }
+            }
+        ));
+        handle.add_controller(click);
+
+        handle
+    }
+
+    // Apply a new width to the column and to the matching cell of every row
+    pub fn set_column_width(&self, column: Column, width: i32, persist: bool) {
+        let imp = self.imp();
+        if let Some(state) = imp
+            .columns
+            .borrow_mut()
+            .iter_mut()
+            .find(|c| c.column == column)
+        {
+            state.width = width;
+        }
+        let index = self
+            .visible_columns()
+            .iter()
+            .position(|c| c.column == column);
+        if persist {
+            self.store_view();
+        }
+        let Some(index) = index else {
+            return;
+        };
+
+        // Flat and tree rows share the same layout of cells
+        for list_box in [imp.file_list.get(), imp.tree_list.get()] {
+            let mut row_opt = list_box.first_child();
+            while let Some(row) = row_opt {
+                let next = row.next_sibling();
+                if let Some(cell) = row
+                    .downcast_ref::<gtk::ListBoxRow>()
+                    .and_then(|r| r.child())
+                    .and_then(|vbox| vbox.first_child())
+                    .and_then(|hbox| Self::nth_child(&hbox, index))
+                {
+                    cell.set_width_request(width);
+                }
+                row_opt = next;
+            }
+        }
+    }
+
+    // Widest rendered value of the column (including its title) plus padding
+    fn fit_column_width(&self, column: Column) -> i32 {
+        let header = self.imp().column_header.get();
+        let text_width = |text: &str| header.create_pango_layout(Some(text)).pixel_size().0;
+        let format = self.display_format();
+
+        let widest = self
+            .imp()
+            .entries
+            .borrow()
+            .iter()
+            .map(|e| text_width(&e.column_display(column, &format)))
+            .chain(std::iter::once(text_width(column.label()) + 16))
+            .max()
+            .unwrap_or(0);
+
+        (widest + 24).max(MIN_COLUMN_WIDTH)
+    }
+
+    fn handle_origin(&self, gesture: &gtk::GestureDrag) -> f64 {
+        gesture
+            .widget()
+            .and_then(|w| w.translate_coordinates(&*self.imp().column_header, 0.0, 0.0))
+            .map(|(x, _)| x)
+            .unwrap_or(0.0)
+    }
+
+    fn nth_child(widget: &gtk::Widget, index: usize) -> Option<gtk::Widget> {
+        let mut child = widget.first_child();
+        for _ in 0..index {
+            child = child?.next_sibling();
+        }
+        child
+    }
+
+    // One boolean "columns.<id>" action per optional column, used by the header menu
+    fn setup_column_actions(&self) {
+        let group = &self.imp().column_actions;
+
+        for state in self.imp().columns.borrow().iter() {
+            if !state.column.is_toggleable() {
+                continue;
+            }
+            let column = state.column;
+            let action =
+                gio::SimpleAction::new_stateful(column.id(), None, &state.visible.to_variant());
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = panel)]
+                self,
+                move |action, _| {
+                    let visible = !action.state().unwrap().get::<bool>().unwrap();
+                    action.set_state(&visible.to_variant());
+                    panel.set_column_visible(column, visible);
+                }
+            ));
+            group.add_action(&action);
+        }
+
+        self.insert_action_group("columns", Some(group));
+    }
+
+    fn sync_column_actions(&self) {
+        let imp = self.imp();
+        for state in imp.columns.borrow().iter() {
+            if let Some(action) = imp
+                .column_actions
+                .lookup_action(state.column.id())
+                .and_downcast::<gio::SimpleAction>()
+            {
+                action.set_state(&state.visible.to_variant());
+            }
+        }
+    }
+
+    fn setup_column_menu(&self) {
+        let menu = gio::Menu::new();
+        for column in Column::ALL.into_iter().filter(|c| c.is_toggleable()) {
+            menu.append(
+                Some(column.label()),
+                Some(&format!("columns.{}", column.id())),
+            );
+        }
+
+        let header = self.imp().column_header.get();
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(gdk::BUTTON_SECONDARY);
+        gesture.connect_pressed(glib::clone!(
+            #[weak]
+            header,
+            move |_, _, x, y| {
+                let popover = gtk::PopoverMenu::from_model(Some(&menu));
+                popover.set_parent(&header);
+                popover.set_has_arrow(false);
+                popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                popover.connect_closed(|p| p.unparent());
+                popover.popup();
+            }
+        ));
+        header.add_controller(gesture);
+    }
+
+    pub fn set_column_visible(&self, column: Column, visible: bool) {
+        if !column.is_toggleable() {
+            return;
+        }
+        {
+            let mut columns = self.imp().columns.borrow_mut();
+            if let Some(state) = columns.iter_mut().find(|c| c.column == column) {
+                state.visible = visible;
+            }
+        }
+        self.store_view();
+        self.rebuild_column_header();
+        self.refresh_view();
+    }
+
+    pub fn move_column(&self, column: Column, before: Column) {
+        if column == before {
+            return;
+        }
+        {
+            let mut columns = self.imp().columns.borrow_mut();
+            let Some(from) = columns.iter().position(|c| c.column == column) else {
+                return;
+            };
+            let state = columns.remove(from);
+            let to = columns
+                .iter()
+                .position(|c| c.column == before)
+                .unwrap_or(columns.len());
+            columns.insert(to, state);
+        }
+        self.store_view();
+        self.rebuild_column_header();
+        self.refresh_view();
+    }
+
+    // Settings
+
+    fn apply_view(&self, view: ViewSettings) {
+        let imp = self.imp();
+        *imp.view_mode.borrow_mut() = view.view_mode;
+        *imp.sort_by.borrow_mut() = view.sort_by;
+        *imp.sort_order.borrow_mut() = view.sort_order;
+        *imp.columns.borrow_mut() = view.columns;
+        self.sync_column_actions();
+        self.rebuild_column_header();
+    }
+
+    fn current_view(&self) -> ViewSettings {
+        let imp = self.imp();
+        ViewSettings {
+            view_mode: imp.view_mode.borrow().clone(),
+            sort_by: imp.sort_by.borrow().clone(),
+            sort_order: imp.sort_order.borrow().clone(),
+            columns: imp.columns.borrow().clone(),
+        }
+    }
+
+    // Remember the current view state for the loaded directory; without
+    // per-directory views the one view in use is the default
+    fn store_view(&self) {
+        let path = self.imp().path.borrow().clone();
+        let view = self.current_view();
+        self.update_settings(|s| {
+            if s.remember_directory_views {
+                s.set_view_for(&path, view);
+            } else {
+                s.set_default_view(view);
+            }
+        });
+    }
+
+    // Use the current view state for directories without one of their own
+    pub fn set_view_as_default(&self) {
+        let view = self.current_view();
+        self.update_settings(|s| s.set_default_view(view));
+    }
+
+    fn update_settings(&self, f: impl FnOnce(&mut Settings)) {
+        let Some(settings) = self.imp().settings.borrow().clone() else {
+            return;
+        };
+        f(&mut settings.borrow_mut());
+        // A panel that is not in a window yet has no application to batch
+        // the write; that only happens while it is being set up
+        let app = self
+            .root()
+            .and_downcast::<gtk::Window>()
+            .and_then(|window| window.application())
+            .and_downcast::<OwlApplication>();
+        match app {
+            Some(app) => app.save_settings_later(),
+            None => settings.borrow().save(),
+        }
+    }
+
     // Utilities
 
     fn clear_list_box(list_box: &gtk::ListBox) {
@@ -364,3 +1936,185 @@ impl OwlContentPanel {
         }
     }
 }
+
+fn tree_row_entry(row: &gtk::TreeListRow) -> Option<FileEntry> {
+    let item = row.item().and_downcast::<glib::BoxedAnyObject>()?;
+    let entry = item.borrow::<FileEntry>().clone();
+    Some(entry)
+}
+
+// Sort key computed ahead of sorting; see `sort_entries`
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    None,
+    Text(String),
+    Count(Option<usize>),
+}
+
+fn compare_names(a: &str, b: &str, options: &SortOptions) -> Ordering {
+    let (a, b) = if options.ignore_punctuation {
+        (strip_leading_punctuation(a), strip_leading_punctuation(b))
+    } else {
+        (a, b)
+    };
+
+    match (options.locale, options.natural) {
+        // GLib's filename keys follow LC_COLLATE and compare digit runs numerically
+        (true, true) => {
+            glib::FilenameCollationKey::from(a).cmp(&glib::FilenameCollationKey::from(b))
+        }
+        (true, false) => glib::CollationKey::from(a).cmp(&glib::CollationKey::from(b)),
+        (false, true) => natural_cmp(&a.to_lowercase(), &b.to_lowercase()),
+        (false, false) => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+// Character positions of `filter` in `name`, ignoring case: a substring if there
+// is one, otherwise the characters in order (fuzzy); None if they do not all appear
+fn filter_positions(name: &str, filter: &str) -> Option<Vec<usize>> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let name: Vec<char> = name.chars().map(lower).collect();
+    let filter: Vec<char> = filter.chars().map(lower).collect();
+    if filter.is_empty() {
+        return Some(Vec::new());
+    }
+
+    if let Some(start) = name
+        .windows(filter.len())
+        .position(|w| w == filter.as_slice())
+    {
+        return Some((start..start + filter.len()).collect());
+    }
+
+    let mut chars = name.iter().enumerate();
+    filter
+        .iter()
+        .map(|f| chars.find(|(_, c)| *c == f).map(|(i, _)| i))
+        .collect()
+}
+
+fn highlight_markup(name: &str, positions: &[usize]) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let text = glib::markup_escape_text(&c.to_string());
+            if positions.contains(&i) {
+                format!("<b>{}</b>", text)
+            } else {
+                text.to_string()
+            }
+        })
+        .collect()
+}
+
+fn strip_leading_punctuation(name: &str) -> &str {
+    let stripped = name.trim_start_matches(|c: char| !c.is_alphanumeric());
+    if stripped.is_empty() {
+        name
+    } else {
+        stripped
+    }
+}
+
+// Compares runs of digits by value, everything else character by character
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_run = take_digits(&mut a);
+                let y_run = take_digits(&mut b);
+                let x_num = x_run.trim_start_matches('0');
+                let y_num = y_run.trim_start_matches('0');
+                let ord = x_num
+                    .len()
+                    .cmp(&y_num.len())
+                    .then_with(|| x_num.cmp(y_num))
+                    .then_with(|| x_run.len().cmp(&y_run.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        run.push(c);
+    }
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file2"), Ordering::Greater);
+        assert_eq!(natural_cmp("file007", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10.0", "v1.9.3"), Ordering::Greater);
+        // Longer than any integer type
+        assert_eq!(
+            natural_cmp("x99999999999999999999", "x100000000000000000000"),
+            Ordering::Less
+        );
+
+        let mut names = vec!["img12", "img2", "img", "img1", "img10b", "img10a"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["img", "img1", "img2", "img10a", "img10b", "img12"]);
+    }
+
+    #[test]
+    fn natural_cmp_breaks_ties() {
+        assert_eq!(natural_cmp("same9", "same9"), Ordering::Equal);
+        assert_eq!(natural_cmp("", ""), Ordering::Equal);
+        // Equal values: fewer leading zeros first, so the order is total
+        assert_eq!(natural_cmp("file1", "file01"), Ordering::Less);
+        assert_eq!(natural_cmp("0", "00"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("1a", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn compare_names_without_locale() {
+        let options = |natural, ignore_punctuation| SortOptions {
+            natural,
+            locale: false,
+            ignore_punctuation,
+            folders_first: true,
+        };
+        assert_eq!(
+            compare_names("Track 9", "track 10", &options(true, false)),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_names("Track 9", "track 10", &options(false, false)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_names("_notes", "apples", &options(false, true)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_names("_notes", "apples", &options(false, false)),
+            Ordering::Less
+        );
+        assert_eq!(strip_leading_punctuation("..."), "...");
+    }
+}
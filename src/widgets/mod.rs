@@ -1,3 +1,4 @@
 pub mod content_panel;
 pub mod navbar;
+pub mod preview_pane;
 pub mod side_panel;
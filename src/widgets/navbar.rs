@@ -1,17 +1,68 @@
+use crate::file_entry::FileEntry;
+use crate::location;
+use crate::search_filters::Kind;
+use gtk::gdk;
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk4 as gtk;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod imp {
     use super::*;
+    use std::cell::RefCell;
 
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(file = "../../data/navbar.ui")]
     pub struct OwlNavBar {
+        #[template_child]
+        pub back_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub forward_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub path_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub crumbs_scroll: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub crumbs: TemplateChild<gtk::Box>,
         #[template_child]
         pub search: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub query_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub search_spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub search_options: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub filter_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub kind_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub modified_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub min_size_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub max_size_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub owner_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub permissions_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub depth_spin: TemplateChild<gtk::SpinButton>,
+        pub path: RefCell<PathBuf>,
+        // Deepest folder shown in the breadcrumbs; going up keeps it so the
+        // way back down stays one click away
+        pub crumb_path: RefCell<PathBuf>,
+        // Folder suggestions under the path entry, as full entry texts
+        pub completion_popover: gtk::Popover,
+        pub completion_list: gtk::ListBox,
+        pub completions: RefCell<Vec<String>>,
+        // The entry text is being replaced by a suggestion, not typed
+        pub completing: RefCell<bool>,
+        // History of the active pane, nearest last, for the button menus
+        pub back_history: RefCell<Vec<PathBuf>>,
+        pub forward_history: RefCell<Vec<PathBuf>>,
     }
 
     #[glib::object_subclass]
@@ -29,7 +80,18 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for OwlNavBar {}
+    impl ObjectImpl for OwlNavBar {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_filters();
+            self.obj().setup_location();
+            self.obj().setup_history_menus();
+        }
+
+        fn dispose(&self) {
+            self.completion_popover.unparent();
+        }
+    }
     impl WidgetImpl for OwlNavBar {}
     impl BoxImpl for OwlNavBar {}
 }
@@ -53,6 +115,534 @@ impl OwlNavBar {
     }
 
     pub fn set_path(&self, path: &PathBuf) {
-        self.imp().search.set_text(&path.to_string_lossy());
+        let imp = self.imp();
+        imp.search.set_text(&path.to_string_lossy());
+        *imp.path.borrow_mut() = path.clone();
+        if !imp.crumb_path.borrow().starts_with(path) {
+            *imp.crumb_path.borrow_mut() = path.clone();
+        }
+        self.rebuild_crumbs();
+        self.show_crumbs();
+    }
+
+    pub fn set_history(&self, back: &[PathBuf], forward: &[PathBuf]) {
+        let imp = self.imp();
+        *imp.back_history.borrow_mut() = back.to_vec();
+        *imp.forward_history.borrow_mut() = forward.to_vec();
+    }
+
+    fn setup_history_menus(&self) {
+        let imp = self.imp();
+        for (button, forward) in [(&*imp.back_button, false), (&*imp.forward_button, true)] {
+            let click = gtk::GestureClick::new();
+            click.set_button(gdk::BUTTON_SECONDARY);
+            click.connect_pressed(glib::clone!(
+                #[weak(rename_to = navbar)]
+                self,
+                move |_, _, _, _| navbar.show_history_menu(forward)
+            ));
+            button.add_controller(click);
+
+            // Claiming the press keeps the button from also going one step
+            let long_press = gtk::GestureLongPress::new();
+            long_press.set_propagation_phase(gtk::PropagationPhase::Capture);
+            long_press.connect_pressed(glib::clone!(
+                #[weak(rename_to = navbar)]
+                self,
+                move |gesture, _, _| {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                    navbar.show_history_menu(forward);
+                }
+            ));
+            button.add_controller(long_press);
+        }
+    }
+
+    // The nearest folder comes first; each item jumps that many steps
+    fn show_history_menu(&self, forward: bool) {
+        let imp = self.imp();
+        let (history, button, action) = if forward {
+            (
+                &imp.forward_history,
+                &*imp.forward_button,
+                "win.go-forward-steps",
+            )
+        } else {
+            (&imp.back_history, &*imp.back_button, "win.go-back-steps")
+        };
+        let history = history.borrow();
+        if history.is_empty() {
+            return;
+        }
+
+        let menu = gio::Menu::new();
+        for (steps, path) in history.iter().rev().enumerate() {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string());
+            let item = gio::MenuItem::new(Some(&name), None);
+            item.set_action_and_target_value(Some(action), Some(&(steps as u32 + 1).to_variant()));
+            menu.append_item(&item);
+        }
+
+        let popover = gtk::PopoverMenu::from_model(Some(&menu));
+        popover.set_parent(button);
+        popover.set_has_arrow(false);
+        popover.connect_closed(|p| p.unparent());
+        popover.popup();
+    }
+
+    // Swap the breadcrumbs for the path entry with its text selected, ready
+    // to type a new location
+    pub fn focus_location(&self) {
+        let imp = self.imp();
+        imp.path_stack.set_visible_child_name("entry");
+        imp.search.grab_focus();
+        imp.search.select_region(0, -1);
+    }
+
+    fn show_crumbs(&self) {
+        let imp = self.imp();
+        imp.completion_popover.popdown();
+        imp.path_stack.set_visible_child_name("crumbs");
+        imp.search.set_text(&imp.path.borrow().to_string_lossy());
+    }
+
+    // Keeps the entry open, marked as invalid, until the text is edited
+    pub fn show_error(&self, message: &str) {
+        let imp = self.imp();
+        imp.completion_popover.popdown();
+        imp.search.add_css_class("error");
+        imp.search.set_tooltip_text(Some(message));
+        if imp.path_stack.visible_child_name().as_deref() != Some("entry") {
+            self.focus_location();
+        }
+    }
+
+    // Clicking beside the breadcrumbs edits the path; Enter, Escape or leaving
+    // the entry brings them back
+    fn setup_location(&self) {
+        let imp = self.imp();
+
+        let click = gtk::GestureClick::new();
+        click.connect_released(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |_, _, _, _| navbar.focus_location()
+        ));
+        imp.crumbs_scroll.add_controller(click);
+
+        // Suggestions follow the typed text; the popover never takes the focus
+        imp.completion_list
+            .set_selection_mode(gtk::SelectionMode::Single);
+        imp.completion_popover
+            .set_child(Some(&*imp.completion_list));
+        imp.completion_popover.set_autohide(false);
+        imp.completion_popover.set_has_arrow(false);
+        imp.completion_popover
+            .set_position(gtk::PositionType::Bottom);
+        imp.completion_popover.set_halign(gtk::Align::Start);
+        imp.completion_popover.set_parent(&*imp.search);
+        imp.completion_list.connect_row_activated(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |_, row| {
+                let text = navbar
+                    .imp()
+                    .completions
+                    .borrow()
+                    .get(row.index() as usize)
+                    .cloned();
+                if let Some(text) = text {
+                    navbar.set_entry_text(&text);
+                    navbar.update_completions();
+                }
+            }
+        ));
+        imp.search.connect_changed(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |entry| {
+                entry.remove_css_class("error");
+                entry.set_tooltip_text(None);
+                if !*navbar.imp().completing.borrow() {
+                    navbar.update_completions();
+                }
+            }
+        ));
+
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, keyval, _, _| navbar.location_key(keyval)
+        ));
+        imp.search.add_controller(keys);
+
+        let focus = gtk::EventControllerFocus::new();
+        focus.connect_leave(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |_| navbar.show_crumbs()
+        ));
+        imp.search.add_controller(focus);
+    }
+
+    // Tab completes the folder name as far as the suggestions agree, Up and
+    // Down pick a suggestion, Escape closes them and then the entry
+    fn location_key(&self, keyval: gdk::Key) -> glib::Propagation {
+        let imp = self.imp();
+        let popover = &imp.completion_popover;
+        match keyval {
+            gdk::Key::Tab => {
+                let text = imp.search.text().to_string();
+                let candidates = location::completions(&text, &imp.path.borrow());
+                // Without candidates Tab moves the focus as usual
+                if candidates.is_empty() {
+                    return glib::Propagation::Proceed;
+                }
+                if let Some(prefix) = location::common_prefix(&candidates) {
+                    if prefix.chars().count() > text.chars().count() {
+                        self.set_entry_text(&prefix);
+                        self.update_completions();
+                    }
+                }
+            }
+            gdk::Key::Down | gdk::Key::Up if popover.is_visible() => {
+                let list = &imp.completion_list;
+                let count = imp.completions.borrow().len() as i32;
+                let index = match (list.selected_row().map(|r| r.index()), keyval) {
+                    (None, gdk::Key::Down) => 0,
+                    (None, _) => count - 1,
+                    (Some(i), gdk::Key::Down) => (i + 1) % count,
+                    (Some(i), _) => (i + count - 1) % count,
+                };
+                list.select_row(list.row_at_index(index).as_ref());
+                let text = imp.completions.borrow().get(index as usize).cloned();
+                if let Some(text) = text {
+                    imp.completing.replace(true);
+                    self.set_entry_text(&text);
+                    imp.completing.replace(false);
+                }
+            }
+            gdk::Key::Escape if popover.is_visible() => popover.popdown(),
+            gdk::Key::Escape => self.show_crumbs(),
+            _ => return glib::Propagation::Proceed,
+        }
+        glib::Propagation::Stop
+    }
+
+    fn set_entry_text(&self, text: &str) {
+        let entry = &self.imp().search;
+        entry.set_text(text);
+        entry.set_position(-1);
+    }
+
+    fn update_completions(&self) {
+        let imp = self.imp();
+        let popover = &imp.completion_popover;
+        if imp.path_stack.visible_child_name().as_deref() != Some("entry") {
+            popover.popdown();
+            return;
+        }
+        let text = imp.search.text().to_string();
+        let mut candidates = location::completions(&text, &imp.path.borrow());
+        candidates.truncate(12);
+        // Nothing to suggest once the only match has been typed out
+        if candidates.is_empty() || candidates == [text] {
+            imp.completions.borrow_mut().clear();
+            popover.popdown();
+            return;
+        }
+
+        let list = &imp.completion_list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        for candidate in &candidates {
+            let name = candidate.trim_end_matches('/');
+            let name = name.rsplit('/').next().unwrap_or(name);
+            let label = gtk::Label::new(Some(name));
+            label.set_xalign(0.0);
+            label.set_margin_start(6);
+            label.set_margin_end(6);
+            let row = gtk::ListBoxRow::new();
+            // Keep the keyboard focus in the entry
+            row.set_focusable(false);
+            row.set_child(Some(&label));
+            list.append(&row);
+        }
+        *imp.completions.borrow_mut() = candidates;
+        popover.popup();
+    }
+
+    // One button per component of `crumb_path`; those past the current folder
+    // are dimmed
+    fn rebuild_crumbs(&self) {
+        let imp = self.imp();
+        let crumbs = imp.crumbs.get();
+        while let Some(child) = crumbs.first_child() {
+            crumbs.remove(&child);
+        }
+
+        let current = imp.path.borrow().clone();
+        let mut path = PathBuf::new();
+        let mut current_button = None;
+        for component in imp.crumb_path.borrow().components() {
+            if path.parent().is_some() {
+                let separator = gtk::Label::new(Some("/"));
+                separator.add_css_class("dim-label");
+                crumbs.append(&separator);
+            }
+            path.push(component);
+            let forward = path != current && !current.starts_with(&path);
+            let button = self.make_crumb(&path, path == current, forward);
+            if path == current {
+                current_button = Some(button.clone());
+            }
+            crumbs.append(&button);
+        }
+
+        // Scroll the current folder into view once the buttons have a size
+        if let Some(button) = current_button {
+            glib::idle_add_local_once(glib::clone!(
+                #[weak(rename_to = navbar)]
+                self,
+                move || {
+                    let imp = navbar.imp();
+                    let Some((x, _)) = button.translate_coordinates(&*imp.crumbs, 0.0, 0.0) else {
+                        return;
+                    };
+                    imp.crumbs_scroll
+                        .hadjustment()
+                        .clamp_page(x, x + button.width() as f64);
+                }
+            ));
+        }
+    }
+
+    fn make_crumb(&self, path: &Path, current: bool, forward: bool) -> gtk::Button {
+        let button = gtk::Button::new();
+        button.add_css_class("flat");
+        let target = path.to_string_lossy().to_string();
+        button.set_action_name(Some("win.navigate"));
+        button.set_action_target(Some(&target));
+
+        match path.file_name() {
+            Some(name) => {
+                let name = name.to_string_lossy();
+                let label = gtk::Label::new(None);
+                if current {
+                    label.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&name)));
+                } else {
+                    label.set_text(&name);
+                }
+                if forward {
+                    label.add_css_class("dim-label");
+                }
+                button.set_child(Some(&label));
+            }
+            None => {
+                button.set_icon_name("drive-harddisk-symbolic");
+                button.set_tooltip_text(Some("File System"));
+            }
+        }
+
+        // Right-click lists the folders next to this one
+        let menu_path = path.to_path_buf();
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(gdk::BUTTON_SECONDARY);
+        gesture.connect_pressed(glib::clone!(
+            #[weak]
+            button,
+            move |_, _, _, _| {
+                let popover = gtk::PopoverMenu::from_model(Some(&sibling_menu(&menu_path)));
+                popover.set_parent(&button);
+                popover.set_has_arrow(false);
+                popover.connect_closed(|p| p.unparent());
+                popover.popup();
+            }
+        ));
+        button.add_controller(gesture);
+
+        // Files dropped on a crumb are moved into that folder
+        let drop = gtk::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::MOVE);
+        drop.connect_drop(glib::clone!(
+            #[weak]
+            button,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Ok(files) = value.get::<gdk::FileList>() else {
+                    return false;
+                };
+                let sources: Vec<String> = files
+                    .files()
+                    .iter()
+                    .filter_map(|f| f.path())
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect();
+                let param = (sources, target.clone()).to_variant();
+                button
+                    .activate_action("win.move-into", Some(&param))
+                    .is_ok()
+            }
+        ));
+        button.add_controller(drop);
+        button
+    }
+
+    // Swap the path entry for the query entry and search options
+    pub fn set_search_mode(&self, active: bool) {
+        let imp = self.imp();
+        imp.path_stack.set_visible(!active);
+        imp.query_entry.set_visible(active);
+        imp.search_options.set_visible(active);
+        imp.filter_button.set_visible(active);
+        if active {
+            imp.query_entry.grab_focus();
+        } else {
+            self.set_searching(false);
+        }
+    }
+
+    pub fn is_search_mode(&self) -> bool {
+        self.imp().query_entry.is_visible()
+    }
+
+    pub fn query(&self) -> String {
+        self.imp().query_entry.text().to_string()
+    }
+
+    pub fn set_query(&self, query: &str) {
+        self.imp().query_entry.set_text(query);
+    }
+
+    // Spinner shown while a search is still walking the tree
+    pub fn set_searching(&self, searching: bool) {
+        let spinner = &self.imp().search_spinner;
+        spinner.set_visible(searching);
+        spinner.set_spinning(searching);
+    }
+
+    pub fn connect_query_changed<F: Fn(&str) + 'static>(&self, f: F) {
+        self.imp().query_entry.connect_search_changed(move |entry| {
+            f(entry.text().as_str());
+        });
+    }
+
+    // Replaces the `key:` filter in the query, or removes it for an empty value
+    pub fn set_filter(&self, key: &str, value: &str) {
+        let prefix = format!("{}:", key);
+        let query = self.query();
+        let mut words: Vec<&str> = query
+            .split_whitespace()
+            .filter(|w| !w.starts_with(&prefix))
+            .collect();
+        let token = format!("{}{}", prefix, value);
+        if !value.is_empty() {
+            words.push(&token);
+        }
+        self.imp().query_entry.set_text(&words.join(" "));
+    }
+
+    fn setup_filters(&self) {
+        let imp = self.imp();
+
+        imp.kind_dropdown.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |dropdown| {
+                let kind = (dropdown.selected() as usize)
+                    .checked_sub(1)
+                    .and_then(|i| Kind::ALL.get(i));
+                navbar.set_filter("type", kind.map(|k| k.id()).unwrap_or_default());
+            }
+        ));
+        imp.modified_dropdown.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |dropdown| {
+                let age = match dropdown.selected() {
+                    1 => "<1d",
+                    2 => "<7d",
+                    3 => "<30d",
+                    4 => "<1y",
+                    _ => "",
+                };
+                navbar.set_filter("modified", age);
+            }
+        ));
+
+        // Both size bounds share one `size:` filter
+        for entry in [&*imp.min_size_entry, &*imp.max_size_entry] {
+            entry.connect_changed(glib::clone!(
+                #[weak(rename_to = navbar)]
+                self,
+                move |_| {
+                    let imp = navbar.imp();
+                    let min = imp.min_size_entry.text();
+                    let max = imp.max_size_entry.text();
+                    let value = match (min.trim(), max.trim()) {
+                        ("", "") => String::new(),
+                        (min, "") => format!(">{}", min),
+                        ("", max) => format!("<{}", max),
+                        (min, max) => format!("{}..{}", min, max),
+                    };
+                    navbar.set_filter("size", &value);
+                }
+            ));
+        }
+        imp.owner_entry.connect_changed(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |entry| navbar.set_filter("owner", entry.text().trim())
+        ));
+        imp.permissions_entry.connect_changed(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |entry| navbar.set_filter("perm", entry.text().trim())
+        ));
+        imp.depth_spin.connect_value_changed(glib::clone!(
+            #[weak(rename_to = navbar)]
+            self,
+            move |spin| {
+                let depth = spin.value_as_int();
+                let value = if depth > 0 {
+                    depth.to_string()
+                } else {
+                    String::new()
+                };
+                navbar.set_filter("depth", &value);
+            }
+        ));
+    }
+
+    pub fn connect_stop_search<F: Fn() + 'static>(&self, f: F) {
+        self.imp().query_entry.connect_stop_search(move |_| f());
+    }
+}
+
+// Folders in the same parent as `path`, or the top-level folders for the root
+fn sibling_menu(path: &Path) -> gio::Menu {
+    let parent = path.parent().unwrap_or(path);
+    let mut folders: Vec<FileEntry> = FileEntry::list_directory(parent)
+        .into_iter()
+        .filter(|e| e.is_dir && !e.name.starts_with('.'))
+        .collect();
+    folders.sort_by_key(|e| e.name.to_lowercase());
+
+    let menu = gio::Menu::new();
+    for folder in folders {
+        let item = gio::MenuItem::new(Some(&folder.name), None);
+        item.set_action_and_target_value(
+            Some("win.navigate"),
+            Some(&folder.path.to_string_lossy().to_string().to_variant()),
+        );
+        menu.append_item(&item);
     }
+    menu
 }
@@ -0,0 +1,227 @@
+use crate::file_entry::{format_size, FileEntry};
+use crate::preview::{self, Preview};
+use crate::types::{Column, DisplayFormat, SizeUnits};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk4 as gtk;
+use std::time::Duration;
+
+// Loading waits this long after the selection last changed, so moving
+// through a list only loads where it stops
+const LOAD_DELAY: Duration = Duration::from_millis(120);
+
+mod imp {
+    use super::*;
+    use std::cell::Cell;
+
+    #[derive(Default, gtk::CompositeTemplate)]
+    #[template(file = "../../data/preview_pane.ui")]
+    pub struct OwlPreviewPane {
+        #[template_child]
+        pub icon: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub text_view: TemplateChild<gtk::TextView>,
+        #[template_child]
+        pub picture: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub message_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub truncated_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub metadata_grid: TemplateChild<gtk::Grid>,
+        // Bumped for every shown entry; results of older loads are dropped
+        pub generation: Cell<u64>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OwlPreviewPane {
+        const NAME: &'static str = "OwlPreviewPane";
+        type Type = super::OwlPreviewPane;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OwlPreviewPane {
+        fn constructed(&self) {
+            self.parent_constructed();
+            preview::setup_tags(&self.text_view.buffer());
+        }
+    }
+
+    impl WidgetImpl for OwlPreviewPane {}
+    impl BoxImpl for OwlPreviewPane {}
+}
+
+glib::wrapper! {
+    pub struct OwlPreviewPane(ObjectSubclass<imp::OwlPreviewPane>)
+        @extends gtk::Widget, gtk::Box,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl OwlPreviewPane {
+    // Shows `entry`, or the placeholder for None. The metadata appears at
+    // once, the contents once a worker thread has read them.
+    pub fn show(&self, entry: Option<FileEntry>, format: &DisplayFormat) {
+        let imp = self.imp();
+        let generation = imp.generation.get() + 1;
+        imp.generation.set(generation);
+        imp.truncated_label.set_visible(false);
+        imp.spinner.stop();
+
+        let grid = &imp.metadata_grid;
+        while let Some(child) = grid.first_child() {
+            grid.remove(&child);
+        }
+
+        let Some(entry) = entry else {
+            imp.icon.set_icon_name(None);
+            imp.name_label.set_text("");
+            imp.stack.set_visible_child_name("empty");
+            return;
+        };
+        imp.icon.set_icon_name(Some(entry.icon_name()));
+        imp.name_label.set_text(&entry.name);
+        imp.name_label.set_tooltip_text(Some(&entry.name));
+        self.fill_metadata(&entry, format);
+        imp.spinner.start();
+
+        let size_units = format.size_units;
+        glib::timeout_add_local_once(
+            LOAD_DELAY,
+            glib::clone!(
+                #[weak(rename_to = pane)]
+                self,
+                move || {
+                    if pane.imp().generation.get() != generation {
+                        return;
+                    }
+                    preview::load_async(
+                        entry,
+                        glib::clone!(
+                            #[weak]
+                            pane,
+                            move |preview| {
+                                if pane.imp().generation.get() == generation {
+                                    pane.show_preview(preview, size_units);
+                                }
+                            }
+                        ),
+                    );
+                }
+            ),
+        );
+    }
+
+    fn show_preview(&self, preview: Preview, size_units: SizeUnits) {
+        let imp = self.imp();
+        imp.spinner.stop();
+        let buffer = imp.text_view.buffer();
+        match preview {
+            Preview::Text {
+                text,
+                highlights,
+                truncated,
+            } => {
+                preview::set_text(&buffer, &text, &highlights);
+                self.show_truncated(truncated, "Only the start of the file is shown");
+                imp.stack.set_visible_child_name("text");
+            }
+            Preview::Binary { dump, truncated } => {
+                buffer.set_text(&dump);
+                self.show_truncated(truncated, "Only the first bytes are shown");
+                imp.stack.set_visible_child_name("text");
+            }
+            Preview::Image(texture) => {
+                imp.picture.set_paintable(Some(&texture));
+                self.add_metadata(
+                    "Dimensions",
+                    &format!("{} × {}", texture.width(), texture.height()),
+                );
+                imp.stack.set_visible_child_name("image");
+            }
+            Preview::Folder {
+                folders,
+                files,
+                size,
+            } => {
+                let text = format!(
+                    "{} folder{}, {} file{}\n{} in files",
+                    folders,
+                    if folders == 1 { "" } else { "s" },
+                    files,
+                    if files == 1 { "" } else { "s" },
+                    format_size(size, size_units),
+                );
+                imp.message_label.set_text(&text);
+                imp.stack.set_visible_child_name("message");
+            }
+            Preview::Media(info) => {
+                let text = if info.is_empty() {
+                    "Install ffprobe (FFmpeg) to see the duration and codecs"
+                } else {
+                    ""
+                };
+                for (name, value) in info {
+                    self.add_metadata(&name, &value);
+                }
+                imp.message_label.set_text(text);
+                imp.stack.set_visible_child_name("message");
+            }
+            Preview::Message(message) => {
+                imp.message_label.set_text(&message);
+                imp.stack.set_visible_child_name("message");
+            }
+        }
+    }
+
+    fn show_truncated(&self, truncated: bool, text: &str) {
+        let label = &self.imp().truncated_label;
+        label.set_text(text);
+        label.set_visible(truncated);
+    }
+
+    fn fill_metadata(&self, entry: &FileEntry, format: &DisplayFormat) {
+        let count_or_size = if entry.is_dir {
+            Column::ItemCount
+        } else {
+            Column::Size
+        };
+        let columns = [
+            Column::Type,
+            Column::MimeType,
+            count_or_size,
+            Column::Date,
+            Column::Created,
+            Column::Accessed,
+            Column::Permissions,
+            Column::Owner,
+            Column::Group,
+            Column::Location,
+        ];
+        for column in columns {
+            self.add_metadata(column.label(), &entry.column_display(column, format));
+        }
+        if entry.link_target.is_some() {
+            let column = Column::LinkTarget;
+            self.add_metadata(column.label(), &entry.column_display(column, format));
+        }
+    }
+
+    fn add_metadata(&self, name: &str, value: &str) {
+        preview::add_info_row(&self.imp().metadata_grid, name, value);
+    }
+}
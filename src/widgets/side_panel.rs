@@ -1,3 +1,6 @@
+use crate::settings::SavedSearch;
+use gtk::gdk;
+use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
@@ -37,6 +40,10 @@ mod imp {
         #[template_child]
         pub places_box: TemplateChild<gtk::Box>,
         #[template_child]
+        pub saved_searches_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub saved_searches_box: TemplateChild<gtk::Box>,
+        #[template_child]
         pub devices_box: TemplateChild<gtk::Box>,
         pub bookmarks: RefCell<Vec<Bookmark>>,
     }
@@ -153,6 +160,52 @@ impl OwlSidePanel {
         self.reload_bookmarks();
     }
 
+    // One entry per saved search; they open through window actions
+    pub fn set_saved_searches(&self, searches: &[SavedSearch]) {
+        let imp = self.imp();
+        let saved_box = imp.saved_searches_box.get();
+        while let Some(child) = saved_box.first_child() {
+            saved_box.remove(&child);
+        }
+        imp.saved_searches_label.set_visible(!searches.is_empty());
+
+        for search in searches {
+            let btn = Self::make_button("folder-saved-search-symbolic", &search.name, &search.root);
+            btn.set_tooltip_text(Some(&format!(
+                "{} in {}",
+                search.query,
+                search.root.display()
+            )));
+            btn.set_action_name(Some("win.open-saved-search"));
+            btn.set_action_target(Some(&search.name));
+
+            let menu = gio::Menu::new();
+            let item = gio::MenuItem::new(Some("Remove"), None);
+            item.set_action_and_target_value(
+                Some("win.remove-saved-search"),
+                Some(&search.name.to_variant()),
+            );
+            menu.append_item(&item);
+
+            let gesture = gtk::GestureClick::new();
+            gesture.set_button(gdk::BUTTON_SECONDARY);
+            gesture.connect_pressed(glib::clone!(
+                #[weak]
+                btn,
+                move |_, _, x, y| {
+                    let popover = gtk::PopoverMenu::from_model(Some(&menu));
+                    popover.set_parent(&btn);
+                    popover.set_has_arrow(false);
+                    popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                    popover.connect_closed(|p| p.unparent());
+                    popover.popup();
+                }
+            ));
+            btn.add_controller(gesture);
+            saved_box.append(&btn);
+        }
+    }
+
     fn load_from_disk() -> Vec<Bookmark> {
         let config = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
         let path = PathBuf::from(config).join(".config/gtk-3.0/bookmarks");
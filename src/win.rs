@@ -1,15 +1,32 @@
 use crate::app::OwlApplication;
-use crate::types::{SortBy, SortOrder, ViewMode};
+use crate::commands;
+use crate::editor;
+use crate::file_entry::FileEntry;
+use crate::file_ops;
+use crate::location;
+use crate::palette::{OwlPalette, PaletteItem};
+use crate::preferences::OwlPreferences;
+use crate::quick_look::OwlQuickLook;
+use crate::search::{self, SearchHandle, SearchMode, SearchOptions, SearchQuery};
+use crate::settings::SavedSearch;
+use crate::shortcuts;
+use crate::terminal;
+use crate::types::{Column, SortBy, SortOptions, SortOrder, ViewMode};
 use crate::widgets::content_panel::OwlContentPanel;
 use crate::widgets::navbar::OwlNavBar;
+use crate::widgets::preview_pane::OwlPreviewPane;
 use crate::widgets::side_panel::OwlSidePanel;
+use gtk::gdk;
 use gtk::gio;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk4 as gtk;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
 
 mod imp {
     use super::*;
@@ -17,18 +34,46 @@ mod imp {
     #[derive(Default, gtk::CompositeTemplate)]
     #[template(file = "../data/window.ui")]
     pub struct OwlWindow {
-        #[template_child]
-        pub content_panel: TemplateChild<OwlContentPanel>,
         #[template_child]
         pub navbar: TemplateChild<OwlNavBar>,
         #[template_child]
         pub side_panel: TemplateChild<OwlSidePanel>,
         #[template_child]
         pub content_container: TemplateChild<gtk::Box>,
+        // One content panel per tab
+        #[template_child]
+        pub tabs: TemplateChild<gtk::Notebook>,
+        #[template_child]
+        pub split_paned: TemplateChild<gtk::Paned>,
+        #[template_child]
+        pub main_pane: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub main_path_entry: TemplateChild<gtk::Entry>,
+        // Second pane of the split view, hidden until F3
+        #[template_child]
+        pub other_pane: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub other_path_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub other_panel: TemplateChild<OwlContentPanel>,
+        #[template_child]
+        pub paned: TemplateChild<gtk::Paned>,
+        // Beside the panes, hidden until F9
+        #[template_child]
+        pub preview_pane: TemplateChild<OwlPreviewPane>,
+        #[template_child]
+        pub recent_menu: TemplateChild<gio::Menu>,
+        #[template_child]
+        pub toast_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub toast_label: TemplateChild<gtk::Label>,
 
-        pub current_path: RefCell<PathBuf>,
-        pub forward_stack: RefCell<Vec<PathBuf>>,
-        pub history: RefCell<Vec<PathBuf>>,
+        pub toast_timeout: RefCell<Option<glib::SourceId>>,
+        pub search: RefCell<Option<SearchHandle>>,
+        // Query text of the last search started, to ignore repeated change signals
+        pub search_text: RefCell<String>,
+        // The second pane has the focus, so actions apply to it
+        pub other_active: RefCell<bool>,
     }
 
     #[glib::object_subclass]
@@ -41,6 +86,7 @@ mod imp {
             OwlNavBar::ensure_type();
             OwlSidePanel::ensure_type();
             OwlContentPanel::ensure_type();
+            OwlPreviewPane::ensure_type();
             klass.bind_template();
         }
 
@@ -55,16 +101,17 @@ mod imp {
             let obj = self.obj();
             obj.setup_actions();
             obj.setup_signals();
-
-            let current_dir = std::env::current_dir().unwrap_or_else(|_| {
-                PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "/".to_string()))
-            });
-            obj.navigate_to(current_dir, false);
+            obj.set_split(false);
         }
     }
 
     impl WidgetImpl for OwlWindow {}
-    impl WindowImpl for OwlWindow {}
+    impl WindowImpl for OwlWindow {
+        fn close_request(&self) -> glib::Propagation {
+            self.obj().save_geometry();
+            self.parent_close_request()
+        }
+    }
     impl ApplicationWindowImpl for OwlWindow {}
 }
 
@@ -78,7 +125,88 @@ glib::wrapper! {
 
 impl OwlWindow {
     pub fn new(app: &OwlApplication) -> Self {
-        glib::Object::builder().property("application", app).build()
+        let win = Self::empty(app);
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| Self::home_dir());
+        win.new_tab(current_dir, true);
+        win
+    }
+
+    // A window without tabs, to receive one dragged out of another window
+    fn empty(app: &OwlApplication) -> Self {
+        let win: Self = glib::Object::builder().property("application", app).build();
+        win.load_settings(app);
+        win
+    }
+
+    // Restores geometry and global view options saved by a previous session
+    fn load_settings(&self, app: &OwlApplication) {
+        let imp = self.imp();
+        let settings = app.settings();
+        let show_preview = {
+            let s = settings.borrow();
+            self.set_default_size(s.window.width, s.window.height);
+            self.set_maximized(s.window.maximized);
+            imp.paned.set_position(s.window.paned_position);
+            imp.side_panel.set_saved_searches(&s.saved_searches);
+            self.update_recent_menu(&s.recent_locations);
+            s.window.show_preview
+        };
+        imp.other_panel.set_settings(settings);
+        self.set_preview(show_preview);
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        let imp = self.imp();
+        imp.toast_label.set_text(message);
+        imp.toast_revealer.set_reveal_child(true);
+
+        if let Some(id) = imp.toast_timeout.take() {
+            id.remove();
+        }
+        let id = glib::timeout_add_local_once(
+            std::time::Duration::from_secs(4),
+            glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                move || {
+                    win.imp().toast_timeout.take();
+                    win.imp().toast_revealer.set_reveal_child(false);
+                }
+            ),
+        );
+        imp.toast_timeout.replace(Some(id));
+    }
+
+    pub fn apply_settings(&self) {
+        for panel in self.panels() {
+            panel.apply_settings();
+        }
+        if let Some(app) = self.application().and_downcast::<OwlApplication>() {
+            self.update_recent_menu(&app.settings().borrow().recent_locations);
+        }
+        self.sync_view_actions();
+        if self.imp().navbar.is_search_mode() {
+            self.start_search();
+        }
+    }
+
+    fn save_geometry(&self) {
+        let Some(app) = self.application().and_downcast::<OwlApplication>() else {
+            return;
+        };
+        let settings = app.settings();
+        let mut s = settings.borrow_mut();
+        let (width, height) = self.default_size();
+        s.window.maximized = self.is_maximized();
+        if !s.window.maximized {
+            s.window.width = width;
+            s.window.height = height;
+        }
+        s.window.paned_position = self.imp().paned.position();
+        s.window.show_preview = self.imp().preview_pane.is_visible();
+        drop(s);
+        // Flushed when the application shuts down after its last window
+        app.save_settings_later();
     }
 
     pub fn navbar(&self) -> &OwlNavBar {
@@ -93,25 +221,1175 @@ impl OwlWindow {
         &self.imp().content_container
     }
 
+    // Content panel of the active pane: the selected tab, or the second
+    // pane of the split view while it has the focus. None in a window whose
+    // tab is still being dragged in.
+    pub fn current_panel(&self) -> Option<OwlContentPanel> {
+        if self.is_split() && *self.imp().other_active.borrow() {
+            return Some(self.imp().other_panel.get());
+        }
+        self.tab_panel()
+    }
+
+    fn tab_panel(&self) -> Option<OwlContentPanel> {
+        let tabs = &self.imp().tabs;
+        tabs.nth_page(tabs.current_page()).and_downcast()
+    }
+
+    fn panels(&self) -> Vec<OwlContentPanel> {
+        let tabs = &self.imp().tabs;
+        (0..tabs.n_pages())
+            .filter_map(|i| tabs.nth_page(Some(i)).and_downcast())
+            .chain(std::iter::once(self.imp().other_panel.get()))
+            .collect()
+    }
+
+    fn current_path(&self) -> Option<PathBuf> {
+        Some(self.current_panel()?.imp().path.borrow().clone())
+    }
+
+    // Opens `path` in the active pane
     pub fn navigate_to(&self, path: PathBuf, push_history: bool) {
-        let imp = self.imp();
-        let current = imp.current_path.borrow().clone();
+        if let Some(panel) = self.current_panel() {
+            self.navigate_panel(&panel, path, push_history);
+        }
+    }
+
+    fn navigate_panel(&self, panel: &OwlContentPanel, path: PathBuf, push_history: bool) {
+        let current = panel.imp().path.borrow().clone();
 
         if push_history && current != PathBuf::new() && current != path {
-            imp.history.borrow_mut().push(current);
-            imp.forward_stack.borrow_mut().clear();
+            panel.imp().history.borrow_mut().push(current);
+            panel.imp().forward_stack.borrow_mut().clear();
         }
 
-        *imp.current_path.borrow_mut() = path.clone();
-        imp.navbar.set_path(&path);
-        imp.content_panel.load_directory(&path);
+        self.set_search_mode(false);
+        panel.load_directory(&path);
+        self.update_tab_label(panel);
+        self.active_panel_changed();
+        // Showing the same folder again, as syncing panes can, is no new visit
+        if current != path {
+            if let Some(app) = self.application().and_downcast::<OwlApplication>() {
+                app.record_location(&path);
+            }
+        }
+    }
+
+    // Go → Recent Locations, from the visit history shared by all windows
+    pub fn update_recent_menu(&self, recent: &[PathBuf]) {
+        const SHOWN: usize = 15;
+        let menu = &self.imp().recent_menu;
+        menu.remove_all();
+        for path in recent.iter().take(SHOWN) {
+            let item = gio::MenuItem::new(Some(&path.to_string_lossy()), None);
+            item.set_action_and_target_value(
+                Some("win.navigate"),
+                Some(&path.to_string_lossy().to_string().to_variant()),
+            );
+            menu.append_item(&item);
+        }
+    }
+
+    // Ctrl+P: frecent folders, then bookmarks, then the folders in the current one
+    fn show_jump_palette(&self) {
+        let mut paths = match self.application().and_downcast::<OwlApplication>() {
+            Some(app) => app.settings().borrow().frecent_locations(),
+            None => Vec::new(),
+        };
+        paths.extend(
+            self.side_panel()
+                .imp()
+                .bookmarks
+                .borrow()
+                .iter()
+                .map(|b| b.path.clone()),
+        );
+        if let Some(current) = self.current_path() {
+            paths.extend(
+                FileEntry::list_directory(&current)
+                    .into_iter()
+                    .filter(|e| e.is_dir && !e.name.starts_with('.'))
+                    .map(|e| e.path),
+            );
+        }
+
+        let mut seen = HashSet::new();
+        let items = paths
+            .into_iter()
+            .filter(|path| seen.insert(path.clone()))
+            .map(|path| PaletteItem {
+                title: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "/".to_string()),
+                subtitle: path.to_string_lossy().to_string(),
+                accel: String::new(),
+                action: "win.navigate".to_string(),
+                target: Some(path.to_string_lossy().to_string().to_variant()),
+            })
+            .collect();
+        OwlPalette::new(self, "Jump to a folder", items).present();
+    }
+
+    // Ctrl+Shift+P: every action of the window and the application
+    fn show_command_palette(&self) {
+        let Some(app) = self.application().and_downcast::<OwlApplication>() else {
+            return;
+        };
+        let items = commands::items(
+            app.upcast_ref(),
+            self.upcast_ref(),
+            &app.settings().borrow(),
+        );
+        OwlPalette::new(self, "Run a command", items).present();
+    }
+
+    // Previews the selection in a window of its own; the arrow keys then
+    // move through the panel's entries
+    fn show_quick_look(&self) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let Some(selected) = panel.selected_entry() else {
+            return;
+        };
+        let mut entries = panel.imp().entries.borrow().clone();
+        // Inside an expanded folder of the tree only that entry is shown
+        let index = match entries.iter().position(|e| e.path == selected.path) {
+            Some(index) => index,
+            None => {
+                entries = vec![selected];
+                0
+            }
+        };
+        OwlQuickLook::new(self, &panel, entries, index).present();
+    }
+
+    // Moves `steps` entries back in the active pane's history
+    fn go_back(&self, steps: usize) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let mut history = panel.imp().history.borrow_mut();
+        if steps == 0 || steps > history.len() {
+            return;
+        }
+        let mut skipped = history.split_off(history.len() - steps);
+        drop(history);
+        let target = skipped.remove(0);
+
+        let mut forward = panel.imp().forward_stack.borrow_mut();
+        forward.push(panel.imp().path.borrow().clone());
+        forward.extend(skipped.into_iter().rev());
+        drop(forward);
+        self.navigate_to(target, false);
+    }
+
+    fn go_forward(&self, steps: usize) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let mut forward = panel.imp().forward_stack.borrow_mut();
+        if steps == 0 || steps > forward.len() {
+            return;
+        }
+        let mut skipped = forward.split_off(forward.len() - steps);
+        drop(forward);
+        let target = skipped.remove(0);
+
+        let mut history = panel.imp().history.borrow_mut();
+        history.push(panel.imp().path.borrow().clone());
+        history.extend(skipped.into_iter().rev());
+        drop(history);
+        self.navigate_to(target, false);
+    }
+
+    // Shows the current folder again, or re-runs the search while in search mode
+    fn reload(&self) {
+        if self.imp().navbar.is_search_mode() {
+            self.start_search();
+        } else if let Some(panel) = self.current_panel() {
+            let path = panel.imp().path.borrow().clone();
+            panel.load_directory(&path);
+        }
+    }
+
+    // Tabs
+
+    // Opens `path` in a new tab after the selected one
+    pub fn new_tab(&self, path: PathBuf, select: bool) {
+        let tabs = &self.imp().tabs;
+        let panel = OwlContentPanel::new();
+        if let Some(app) = self.application().and_downcast::<OwlApplication>() {
+            panel.set_settings(app.settings());
+        }
+        panel.load_directory(&path);
+        Self::connect_tab(&panel);
+
+        let position = tabs.current_page().map(|page| page + 1);
+        let page = tabs.insert_page(&panel, Some(&Self::make_tab_label(&panel)), position);
+        self.update_tab_label(&panel);
+        if select {
+            tabs.set_current_page(Some(page));
+        }
+    }
+
+    // The last tab takes the window with it
+    pub fn close_tab(&self, panel: &OwlContentPanel) {
+        let tabs = &self.imp().tabs;
+        let Some(page) = tabs.page_num(panel) else {
+            return;
+        };
+        if self.current_panel().as_ref() == Some(panel) && self.imp().navbar.is_search_mode() {
+            self.set_search_mode(false);
+        }
+        tabs.remove_page(Some(page));
+    }
+
+    fn close_other_tabs(&self) {
+        let Some(current) = self.tab_panel() else {
+            return;
+        };
+        for panel in self.panels() {
+            if panel != current {
+                self.close_tab(&panel);
+            }
+        }
+    }
+
+    // Moves `offset` tabs from the selected one, wrapping around
+    fn cycle_tabs(&self, offset: i32) {
+        let tabs = &self.imp().tabs;
+        let count = tabs.n_pages() as i32;
+        if count < 2 {
+            return;
+        }
+        let current = tabs.current_page().unwrap_or(0) as i32;
+        tabs.set_current_page(Some((current + offset).rem_euclid(count) as u32));
+    }
+
+    // Navbar, path bars, menus and actions follow the active pane
+    fn active_panel_changed(&self) {
+        let imp = self.imp();
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        imp.navbar.set_path(&panel.imp().path.borrow());
+        if let Some(tab_panel) = self.tab_panel() {
+            imp.main_path_entry
+                .set_text(&tab_panel.imp().path.borrow().to_string_lossy());
+        }
+        imp.other_path_entry
+            .set_text(&imp.other_panel.imp().path.borrow().to_string_lossy());
+        // The path bar of the inactive pane is dimmed
+        let other_active = *imp.other_active.borrow();
+        for (entry, dim) in [
+            (&imp.main_path_entry, other_active),
+            (&imp.other_path_entry, !other_active),
+        ] {
+            if dim {
+                entry.add_css_class("dim-label");
+            } else {
+                entry.remove_css_class("dim-label");
+            }
+        }
         self.update_nav_actions();
+        self.sync_view_actions();
+        self.update_preview();
     }
 
-    fn update_nav_actions(&self) {
+    // Preview pane
+
+    fn set_preview(&self, show: bool) {
+        self.imp().preview_pane.set_visible(show);
+        self.set_action_state("preview", show.to_variant());
+        self.update_preview();
+    }
+
+    // Shows the selection of the active pane; nothing is read while hidden
+    fn update_preview(&self) {
+        let pane = &self.imp().preview_pane;
+        if !pane.is_visible() {
+            return;
+        }
+        let Some(panel) = self.current_panel() else {
+            pane.show(None, &Default::default());
+            return;
+        };
+        pane.show(panel.selected_entry(), &panel.display_format());
+    }
+
+    // Split view
+
+    fn is_split(&self) -> bool {
+        self.imp().other_pane.is_visible()
+    }
+
+    fn set_split(&self, split: bool) {
+        let imp = self.imp();
+        if imp.navbar.is_search_mode() {
+            self.stop_search();
+        }
+        if split {
+            // The second pane starts where the first one is
+            if *imp.other_panel.imp().path.borrow() == PathBuf::new() {
+                if let Some(path) = self.current_path() {
+                    imp.other_panel.load_directory(&path);
+                }
+            }
+            imp.split_paned.set_position(imp.split_paned.width() / 2);
+        } else {
+            imp.other_active.replace(false);
+        }
+        imp.other_pane.set_visible(split);
+        imp.main_path_entry.set_visible(split);
+        self.set_action_state("split-view", split.to_variant());
+        for name in [
+            "copy-to-other-pane",
+            "move-to-other-pane",
+            "sync-panes",
+            "swap-panes",
+        ] {
+            if let Some(a) = self.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+                a.set_enabled(split);
+            }
+        }
+        self.active_panel_changed();
+    }
+
+    fn set_other_active(&self, active: bool) {
+        if *self.imp().other_active.borrow() == active {
+            return;
+        }
+        // A search belongs to the pane it was started in
+        if self.imp().navbar.is_search_mode() {
+            self.stop_search();
+        }
+        self.imp().other_active.replace(active);
+        self.active_panel_changed();
+    }
+
+    fn other_pane_panel(&self) -> Option<OwlContentPanel> {
+        if !self.is_split() {
+            return None;
+        }
+        if *self.imp().other_active.borrow() {
+            self.tab_panel()
+        } else {
+            Some(self.imp().other_panel.get())
+        }
+    }
+
+    // Copies or moves the selection of the active pane into the other pane's folder
+    fn transfer_to_other_pane(&self, move_files: bool) {
+        let Some(other) = self.other_pane_panel() else {
+            return;
+        };
+        let sources = self.selected_paths();
+        if sources.is_empty() {
+            return;
+        }
+        let dest = other.imp().path.borrow().clone();
+        self.transfer(sources, dest, move_files);
+    }
+
+    // Paths selected in the active pane; empty, with a hint, when there are none
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        let paths: Vec<PathBuf> = self
+            .current_panel()
+            .map(|panel| panel.selected_entries())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        if paths.is_empty() {
+            self.show_toast("Select files or folders first");
+        }
+        paths
+    }
+
+    // Copies or moves `sources` into `dest` off the main thread, stopping at
+    // the first failure
+    fn transfer(&self, sources: Vec<PathBuf>, dest: PathBuf, move_files: bool) {
+        self.run_file_job(move || {
+            sources.iter().try_for_each(|source| {
+                if move_files {
+                    file_ops::move_into(source, &dest).map(|_| ())
+                } else {
+                    file_ops::copy_into(source, &dest).map(|_| ())
+                }
+            })
+        });
+    }
+
+    // Runs `job` off the main thread, then reports its error and lists the
+    // panes again
+    fn run_file_job<F: FnOnce() -> Result<(), String> + Send + 'static>(&self, job: F) {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(job());
+        });
+
+        glib::timeout_add_local(
+            Duration::from_millis(100),
+            glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    match receiver.try_recv() {
+                        Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                        Ok(Err(message)) => win.show_toast(&message),
+                        Ok(Ok(_)) | Err(mpsc::TryRecvError::Disconnected) => {}
+                    }
+                    // Items handled before a failure have changed too
+                    win.reload_panes();
+                    glib::ControlFlow::Break
+                }
+            ),
+        );
+    }
+
+    // Lists the folders of both panes again after files changed; search
+    // results keep their place but lose the files that were moved away
+    fn reload_panes(&self) {
+        let panes = [self.tab_panel(), Some(self.imp().other_panel.get())];
+        for panel in panes.into_iter().flatten() {
+            if panel.is_searching() {
+                panel.remove_missing_results();
+            } else {
+                let path = panel.imp().path.borrow().clone();
+                panel.load_directory(&path);
+            }
+        }
+    }
+
+    // File actions
+
+    // The window's accelerators also fire while a text field has the focus;
+    // Ctrl+C and the like then belong to the text, not to the files
+    fn focused_text(&self) -> Option<gtk::Text> {
+        GtkWindowExt::focus(self).and_downcast::<gtk::Text>()
+    }
+
+    // Puts the selection on the clipboard the way other file managers do, so
+    // it can also be pasted into them
+    fn copy_files(&self, cut: bool) {
+        if let Some(text) = self.focused_text() {
+            let action = if cut {
+                "clipboard.cut"
+            } else {
+                "clipboard.copy"
+            };
+            let _ = text.activate_action(action, None);
+            return;
+        }
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+        let uris: Vec<String> = paths
+            .iter()
+            .map(|p| gio::File::for_path(p).uri().to_string())
+            .collect();
+        let operation = if cut { "cut" } else { "copy" };
+        let gnome = format!("{}\n{}", operation, uris.join("\n"));
+        let uri_list = format!("{}\r\n", uris.join("\r\n"));
+        let names: Vec<String> = paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let provider = gdk::ContentProvider::new_union(&[
+            gdk::ContentProvider::for_bytes(
+                "x-special/gnome-copied-files",
+                &glib::Bytes::from_owned(gnome.into_bytes()),
+            ),
+            gdk::ContentProvider::for_bytes(
+                "text/uri-list",
+                &glib::Bytes::from_owned(uri_list.into_bytes()),
+            ),
+            gdk::ContentProvider::for_value(&names.join("\n").to_value()),
+        ]);
+        if self.clipboard().set_content(Some(&provider)).is_err() {
+            self.show_toast("Could not use the clipboard");
+        }
+    }
+
+    // Copies the files on the clipboard into the active pane's folder, or
+    // moves them when they were cut
+    fn paste_files(&self) {
+        if let Some(text) = self.focused_text() {
+            let _ = text.activate_action("clipboard.paste", None);
+            return;
+        }
+        let Some(dest) = self.current_path() else {
+            return;
+        };
+        let clipboard = self.clipboard();
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            async move {
+                let mime_types = ["x-special/gnome-copied-files", "text/uri-list"];
+                let Ok((stream, mime_type)) = clipboard
+                    .read_future(&mime_types, glib::Priority::DEFAULT)
+                    .await
+                else {
+                    win.show_toast("There are no files to paste");
+                    return;
+                };
+                let mut data = Vec::new();
+                loop {
+                    match stream
+                        .read_bytes_future(8192, glib::Priority::DEFAULT)
+                        .await
+                    {
+                        Ok(bytes) if !bytes.is_empty() => data.extend_from_slice(&bytes),
+                        Ok(_) => break,
+                        Err(e) => {
+                            win.show_toast(&format!("Could not read the clipboard: {}", e));
+                            return;
+                        }
+                    }
+                }
+
+                // "x-special/gnome-copied-files" starts with "copy" or "cut";
+                // a uri-list has comment lines starting with '#'
+                let text = String::from_utf8_lossy(&data);
+                let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+                let cut = mime_type == mime_types[0] && lines.next() == Some("cut");
+                let sources: Vec<PathBuf> = lines
+                    .filter(|l| !l.starts_with('#'))
+                    .filter_map(|uri| gio::File::for_uri(uri).path())
+                    .collect();
+                if sources.is_empty() {
+                    win.show_toast("There are no files to paste");
+                    return;
+                }
+                if cut {
+                    // Cut files can only be pasted once
+                    win.clipboard()
+                        .set_content(None::<&gdk::ContentProvider>)
+                        .ok();
+                }
+                win.transfer(sources, dest, cut);
+            }
+        ));
+    }
+
+    fn select_all(&self) {
+        if let Some(text) = self.focused_text() {
+            let _ = text.activate_action("selection.select-all", None);
+        } else if let Some(panel) = self.current_panel() {
+            panel.select_all();
+        }
+    }
+
+    fn rename_selected(&self) {
+        let Some(entry) = self.current_panel().and_then(|p| p.selected_entry()) else {
+            self.show_toast("Select a file or folder first");
+            return;
+        };
+        self.prompt_name("Rename", "Rename", &entry.name, move |win, name| {
+            if name == entry.name {
+                return;
+            }
+            match file_ops::rename(&entry.path, &name) {
+                Ok(target) => {
+                    win.reload_panes();
+                    if let Some(panel) = win.current_panel() {
+                        panel.select_path(&target);
+                    }
+                }
+                Err(message) => win.show_toast(&message),
+            }
+        });
+    }
+
+    fn trash_selected(&self) {
+        if let Some(text) = self.focused_text() {
+            text.emit_by_name::<()>("delete-from-cursor", &[&gtk::DeleteType::Chars, &1i32]);
+            return;
+        }
+        let paths = self.selected_paths();
+        if !paths.is_empty() {
+            self.run_file_job(move || paths.iter().try_for_each(|p| file_ops::trash(p)));
+        }
+    }
+
+    // Deletes the selection for good, once confirmed unless turned off in the
+    // preferences
+    fn delete_selected(&self) {
+        if let Some(text) = self.focused_text() {
+            let _ = text.activate_action("clipboard.cut", None);
+            return;
+        }
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+        let confirm = self
+            .application()
+            .and_downcast::<OwlApplication>()
+            .is_none_or(|app| app.settings().borrow().confirm_delete);
+        if !confirm {
+            self.run_file_job(move || paths.iter().try_for_each(|p| file_ops::delete(p)));
+            return;
+        }
+        let message = match paths.as_slice() {
+            [path] => format!(
+                "Permanently delete \"{}\"?",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            _ => format!("Permanently delete {} items?", paths.len()),
+        };
+        let dialog = gtk::AlertDialog::builder()
+            .modal(true)
+            .message(message)
+            .detail("Deleted items are not moved to the trash and cannot be restored.")
+            .buttons(["Cancel", "Delete"])
+            .cancel_button(0)
+            .default_button(0)
+            .build();
+        dialog.choose(
+            Some(self),
+            None::<&gio::Cancellable>,
+            glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                move |response| {
+                    if matches!(response, Ok(1)) {
+                        win.run_file_job(move || {
+                            paths.iter().try_for_each(|p| file_ops::delete(p))
+                        });
+                    }
+                }
+            ),
+        );
+    }
+
+    // Shows the active pane's folder in the other pane too
+    fn sync_panes(&self) {
+        if let (Some(other), Some(path)) = (self.other_pane_panel(), self.current_path()) {
+            self.navigate_panel(&other, path, true);
+        }
+    }
+
+    fn swap_panes(&self) {
+        let (Some(other), Some(panel)) = (self.other_pane_panel(), self.current_panel()) else {
+            return;
+        };
+        let path = panel.imp().path.borrow().clone();
+        let other_path = other.imp().path.borrow().clone();
+        self.navigate_panel(&panel, other_path, true);
+        self.navigate_panel(&other, path, true);
+    }
+
+    fn tabs_changed(&self) {
+        let count = self.imp().tabs.n_pages();
+        if count == 0 {
+            self.close();
+            return;
+        }
+        // A single tab needs no tab bar
+        self.imp().tabs.set_show_tabs(count > 1);
+        if let Some(a) = self
+            .lookup_action("close-other-tabs")
+            .and_downcast::<gio::SimpleAction>()
+        {
+            a.set_enabled(count > 1);
+        }
+    }
+
+    // Folder name with a close button, and a menu on right-click
+    fn make_tab_label(panel: &OwlContentPanel) -> gtk::Box {
+        let label = gtk::Label::new(None);
+        label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        label.set_max_width_chars(24);
+
+        let close = gtk::Button::from_icon_name("window-close-symbolic");
+        close.add_css_class("flat");
+        close.set_tooltip_text(Some("Close Tab"));
+        // Tabs can move between windows, so look the window up on each click
+        close.connect_clicked(glib::clone!(
+            #[weak]
+            panel,
+            move |_| {
+                if let Some(win) = panel.root().and_downcast::<OwlWindow>() {
+                    win.close_tab(&panel);
+                }
+            }
+        ));
+
+        let hbox = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        hbox.append(&label);
+        hbox.append(&close);
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Close Tab"), Some("win.close-tab"));
+        menu.append(Some("Close Other Tabs"), Some("win.close-other-tabs"));
+
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(gdk::BUTTON_SECONDARY);
+        gesture.connect_pressed(glib::clone!(
+            #[weak]
+            panel,
+            #[weak]
+            hbox,
+            move |_, _, x, y| {
+                let Some(win) = panel.root().and_downcast::<OwlWindow>() else {
+                    return;
+                };
+                // The menu acts on the selected tab
+                let tabs = &win.imp().tabs;
+                tabs.set_current_page(tabs.page_num(&panel));
+
+                let popover = gtk::PopoverMenu::from_model(Some(&menu));
+                popover.set_parent(&hbox);
+                popover.set_has_arrow(false);
+                popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                popover.connect_closed(|p| p.unparent());
+                popover.popup();
+            }
+        ));
+        hbox.add_controller(gesture);
+        hbox
+    }
+
+    // The tab shows the folder name, with the full path as tooltip
+    fn update_tab_label(&self, panel: &OwlContentPanel) {
+        let Some(tab) = self.imp().tabs.tab_label(panel) else {
+            return;
+        };
+        let path = panel.imp().path.borrow().clone();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        tab.set_tooltip_text(Some(&path.to_string_lossy()));
+        if let Some(label) = tab.first_child().and_downcast::<gtk::Label>() {
+            label.set_text(&name);
+        }
+    }
+
+    // Activating a folder opens it in the tab, the middle button in a new tab
+    fn connect_tab(panel: &OwlContentPanel) {
+        let imp = panel.imp();
+        for list_box in [
+            imp.file_list.get(),
+            imp.tree_list.get(),
+            imp.compact_list.get(),
+            imp.column_list.get(),
+        ] {
+            list_box.connect_row_activated(glib::clone!(
+                #[weak]
+                panel,
+                move |_, row| Self::open_entry(&panel, row.index(), false)
+            ));
+            let gesture = gtk::GestureClick::new();
+            gesture.set_button(gdk::BUTTON_MIDDLE);
+            gesture.connect_pressed(glib::clone!(
+                #[weak]
+                panel,
+                #[weak]
+                list_box,
+                move |_, _, _, y| {
+                    if let Some(row) = list_box.row_at_y(y as i32) {
+                        Self::open_entry(&panel, row.index(), true);
+                    }
+                }
+            ));
+            list_box.add_controller(gesture);
+        }
+
+        // The preview follows the selection of the active pane
+        panel.connect_selection_changed(glib::clone!(
+            #[weak]
+            panel,
+            move || {
+                let Some(win) = panel.root().and_downcast::<OwlWindow>() else {
+                    return;
+                };
+                if win.current_panel().as_ref() == Some(&panel) {
+                    win.update_preview();
+                }
+            }
+        ));
+
+        let flow_box = imp.flow_box.get();
+        flow_box.connect_child_activated(glib::clone!(
+            #[weak]
+            panel,
+            move |_, child| Self::open_entry(&panel, child.index(), false)
+        ));
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(gdk::BUTTON_MIDDLE);
+        gesture.connect_pressed(glib::clone!(
+            #[weak]
+            panel,
+            #[weak]
+            flow_box,
+            move |_, _, x, y| {
+                if let Some(child) = flow_box.child_at_pos(x as i32, y as i32) {
+                    Self::open_entry(&panel, child.index(), true);
+                }
+            }
+        ));
+        flow_box.add_controller(gesture);
+    }
+
+    fn open_entry(panel: &OwlContentPanel, index: i32, new_tab: bool) {
+        let Some(win) = panel.root().and_downcast::<OwlWindow>() else {
+            return;
+        };
+        let entry = usize::try_from(index).ok().and_then(|i| panel.entry_at(i));
+        let Some(entry) = entry.filter(|e| e.is_dir) else {
+            return;
+        };
+        if new_tab {
+            win.new_tab(entry.path, false);
+        } else {
+            win.navigate_panel(panel, entry.path, true);
+        }
+    }
+
+    // Search
+
+    fn set_search_mode(&self, active: bool) {
+        if let Some(a) = self
+            .lookup_action("search")
+            .and_downcast::<gio::SimpleAction>()
+        {
+            a.set_state(&active.to_variant());
+        }
+        self.imp().navbar.set_search_mode(active);
+        if !active {
+            self.cancel_search();
+            self.imp().search_text.borrow_mut().clear();
+        }
+    }
+
+    // Leaves search mode and lists the searched folder again
+    fn stop_search(&self) {
+        self.set_search_mode(false);
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        if panel.is_searching() {
+            let path = panel.imp().path.borrow().clone();
+            panel.load_directory(&path);
+        }
+    }
+
+    fn cancel_search(&self) {
+        if let Some(handle) = self.imp().search.take() {
+            handle.cancel();
+        }
+        self.imp().navbar.set_searching(false);
+    }
+
+    // Match mode and case sensitivity chosen in the search options menu
+    fn search_flags(&self) -> (SearchMode, bool) {
+        let state = |name: &str| self.lookup_action(name).and_then(|a| a.state());
+        let mode = state("search-mode")
+            .and_then(|v| v.get::<String>())
+            .map(|id| SearchMode::from_id(&id))
+            .unwrap_or_default();
+        let case_sensitive = state("search-case-sensitive")
+            .and_then(|v| v.get::<bool>())
+            .unwrap_or(false);
+        (mode, case_sensitive)
+    }
+
+    fn search_query(&self) -> Result<SearchQuery, String> {
+        let (mode, case_sensitive) = self.search_flags();
+        SearchQuery::parse(&self.imp().navbar.query(), mode, case_sensitive)
+    }
+
+    fn search_options(&self) -> SearchOptions {
+        let mut options = SearchOptions {
+            include_hidden: self.current_panel().is_some_and(|p| p.show_hidden_files()),
+            ..Default::default()
+        };
+        if let Some(app) = self.application().and_downcast::<OwlApplication>() {
+            let settings = app.settings();
+            let s = settings.borrow();
+            // Hand-edited settings can hold any number
+            options.max_file_size = s.search.max_file_size_mb.saturating_mul(1024 * 1024);
+            options.respect_gitignore = s.search.respect_gitignore;
+        }
+        options
+    }
+
+    // (Re)starts searching the current folder with the query and options in the navbar
+    fn start_search(&self) {
         let imp = self.imp();
-        let can_back = !imp.history.borrow().is_empty();
-        let can_forward = !imp.forward_stack.borrow().is_empty();
+        self.cancel_search();
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let current = panel.imp().path.borrow().clone();
+        *imp.search_text.borrow_mut() = imp.navbar.query();
+
+        let query = match self.search_query() {
+            Ok(query) => query,
+            Err(message) => {
+                self.show_toast(&message);
+                return;
+            }
+        };
+        if query.is_empty() {
+            panel.load_directory(&current);
+            return;
+        }
+        let matcher = match query.matcher() {
+            Ok(matcher) => matcher,
+            Err(message) => {
+                self.show_toast(&message);
+                return;
+            }
+        };
+
+        let options = SearchOptions {
+            filters: query.filters,
+            ..self.search_options()
+        };
+        let (handle, receiver) = search::spawn(current, matcher, options);
+        panel.begin_search();
+        imp.navbar.set_searching(true);
+        imp.search.replace(Some(handle.clone()));
+
+        // Drain result batches from the walker on the main loop
+        glib::timeout_add_local(
+            Duration::from_millis(50),
+            glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                #[weak]
+                panel,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let imp = win.imp();
+                    if handle.is_cancelled() {
+                        return glib::ControlFlow::Break;
+                    }
+                    // A directory load replaced the results
+                    if !panel.is_searching() {
+                        win.cancel_search();
+                        return glib::ControlFlow::Break;
+                    }
+                    loop {
+                        match receiver.try_recv() {
+                            Ok(batch) => panel.append_search_results(batch),
+                            Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                            Err(mpsc::TryRecvError::Disconnected) => {
+                                imp.search.take();
+                                imp.navbar.set_searching(false);
+                                panel.finish_search();
+                                return glib::ControlFlow::Break;
+                            }
+                        }
+                    }
+                }
+            ),
+        );
+    }
+
+    // Saved searches
+
+    fn set_action_state(&self, name: &str, state: glib::Variant) {
+        if let Some(a) = self.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+            a.set_state(&state);
+        }
+    }
+
+    fn prompt_save_search(&self) {
+        let query = self.imp().navbar.query();
+        if query.trim().is_empty() {
+            self.show_toast("Type a query to save it");
+            return;
+        }
+        let (mode, case_sensitive) = self.search_flags();
+        let Some(root) = self.current_path() else {
+            return;
+        };
+        let initial = query.trim().to_string();
+
+        self.prompt_name("Save Search", "Save", &initial, move |win, name| {
+            let Some(app) = win.application().and_downcast::<OwlApplication>() else {
+                return;
+            };
+            {
+                let settings = app.settings();
+                let mut s = settings.borrow_mut();
+                s.save_search(SavedSearch {
+                    name,
+                    root: root.clone(),
+                    query: query.clone(),
+                    mode,
+                    case_sensitive,
+                });
+            }
+            app.save_settings_later();
+            app.update_saved_searches();
+        });
+    }
+
+    // Re-runs a saved search from its root, like opening a folder
+    fn open_saved_search(&self, name: &str) {
+        let Some(app) = self.application().and_downcast::<OwlApplication>() else {
+            return;
+        };
+        let saved = app
+            .settings()
+            .borrow()
+            .saved_searches
+            .iter()
+            .find(|s| s.name == name)
+            .cloned();
+        let Some(saved) = saved else {
+            return;
+        };
+        if !saved.root.is_dir() {
+            self.show_toast(&format!("{} no longer exists", saved.root.display()));
+            return;
+        }
+
+        self.navigate_to(saved.root.clone(), true);
+        self.set_action_state("search-mode", saved.mode.id().to_variant());
+        self.set_action_state("search-case-sensitive", saved.case_sensitive.to_variant());
+        self.set_search_mode(true);
+        self.imp().navbar.set_query(&saved.query);
+        self.start_search();
+    }
+
+    // Small modal asking for a name; `on_accept` gets the trimmed, non-empty text
+    fn prompt_name<F: Fn(&OwlWindow, String) + 'static>(
+        &self,
+        title: &str,
+        accept_label: &str,
+        initial: &str,
+        on_accept: F,
+    ) {
+        let dialog = gtk::Window::builder()
+            .title(title)
+            .modal(true)
+            .transient_for(self)
+            .resizable(false)
+            .default_width(320)
+            .build();
+
+        let entry = gtk::Entry::builder()
+            .text(initial)
+            .activates_default(true)
+            .build();
+        let cancel = gtk::Button::with_label("Cancel");
+        let accept = gtk::Button::with_label(accept_label);
+        accept.add_css_class("suggested-action");
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::End);
+        buttons.append(&cancel);
+        buttons.append(&accept);
+
+        let vbox = gtk::Box::new(gtk::Orientation::Vertical, 12);
+        vbox.set_margin_top(18);
+        vbox.set_margin_bottom(18);
+        vbox.set_margin_start(18);
+        vbox.set_margin_end(18);
+        vbox.append(&entry);
+        vbox.append(&buttons);
+        dialog.set_child(Some(&vbox));
+        dialog.set_default_widget(Some(&accept));
+
+        cancel.connect_clicked(glib::clone!(
+            #[weak]
+            dialog,
+            move |_| dialog.close()
+        ));
+        accept.connect_clicked(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            #[weak]
+            dialog,
+            #[weak]
+            entry,
+            move |_| {
+                let name = entry.text().trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                dialog.close();
+                on_accept(&win, name);
+            }
+        ));
+        dialog.present();
+    }
+
+    // Reflect the panel state (which may come from per-directory settings) in the menus
+    fn sync_view_actions(&self) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let panel = panel.imp();
+        let options = panel.sort_options.borrow().clone();
+        let sort_id = Column::ALL
+            .into_iter()
+            .find(|c| c.sort_by() == *panel.sort_by.borrow())
+            .map(|c| c.id())
+            .unwrap_or("name");
+        let order = match *panel.sort_order.borrow() {
+            SortOrder::Ascending => "ascending",
+            SortOrder::Descending => "descending",
+        };
+        let view = format!("'{}'", panel.view_mode.borrow().id());
+
+        let states = [
+            ("sort", sort_id.to_variant()),
+            ("order", order.to_variant()),
+            ("view", view.to_variant()),
+            ("show-hidden", panel.show_hidden_files.borrow().to_variant()),
+            ("tree-view", panel.tree_view.borrow().to_variant()),
+            ("sort-natural", options.natural.to_variant()),
+            ("sort-locale", options.locale.to_variant()),
+            (
+                "sort-ignore-punctuation",
+                options.ignore_punctuation.to_variant(),
+            ),
+            ("folders-first", options.folders_first.to_variant()),
+        ];
+        for (name, state) in states {
+            if let Some(a) = self.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+                a.set_state(&state);
+            }
+        }
+    }
+
+    // Flips the boolean state of `action` and stores it in the active pane's
+    // sort options through `set`
+    fn toggle_sort_option(&self, action: &gio::SimpleAction, set: fn(&mut SortOptions, bool)) {
+        let new_state = !action.state().unwrap().get::<bool>().unwrap();
+        action.set_state(&new_state.to_variant());
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let mut options = panel.imp().sort_options.borrow().clone();
+        set(&mut options, new_state);
+        panel.set_sort_options(options);
+    }
+
+    fn update_nav_actions(&self) {
+        let Some(panel) = self.current_panel() else {
+            return;
+        };
+        let can_back = !panel.imp().history.borrow().is_empty();
+        let can_forward = !panel.imp().forward_stack.borrow().is_empty();
+        self.imp().navbar.set_history(
+            &panel.imp().history.borrow(),
+            &panel.imp().forward_stack.borrow(),
+        );
 
         if let Some(a) = self.lookup_action("go-back") {
             a.downcast::<gio::SimpleAction>()
@@ -125,6 +1403,40 @@ impl OwlWindow {
         }
     }
 
+    // Enter in a split pane's path bar; understands the same locations as the
+    // navbar and marks the entry when there is nothing to open
+    fn enter_pane_location(&self, entry: &gtk::Entry, other: bool) {
+        let panel = if other {
+            Some(self.imp().other_panel.get())
+        } else {
+            self.tab_panel()
+        };
+        let Some(panel) = panel else {
+            return;
+        };
+        let current = panel.imp().path.borrow().clone();
+        let error = match location::resolve(&entry.text(), &current) {
+            Some(path) if path.is_dir() => {
+                self.set_other_active(other);
+                self.navigate_to(path, true);
+                return;
+            }
+            // A file opens its folder with the file selected
+            Some(path) if path.exists() => {
+                if let Some(parent) = path.parent() {
+                    self.set_other_active(other);
+                    self.navigate_to(parent.to_path_buf(), true);
+                    panel.select_path(&path);
+                }
+                return;
+            }
+            Some(path) => format!("\"{}\" does not exist", path.display()),
+            None => "Not a valid location".to_string(),
+        };
+        entry.add_css_class("error");
+        entry.set_tooltip_text(Some(&error));
+    }
+
     fn setup_signals(&self) {
         let imp = self.imp();
 
@@ -132,83 +1444,142 @@ impl OwlWindow {
             #[weak(rename_to = win)]
             self,
             move |text| {
-                let path = PathBuf::from(text);
-                if path.is_dir() {
-                    win.navigate_to(path, true);
+                let Some(current) = win.current_path() else {
+                    return;
+                };
+                match location::resolve(text, &current) {
+                    Some(path) if path.is_dir() => win.navigate_to(path, true),
+                    // A file opens its folder with the file selected
+                    Some(path) if path.exists() => {
+                        if let Some(parent) = path.parent() {
+                            win.navigate_to(parent.to_path_buf(), true);
+                            if let Some(panel) = win.current_panel() {
+                                panel.select_path(&path);
+                            }
+                        }
+                    }
+                    Some(path) => win
+                        .imp()
+                        .navbar
+                        .show_error(&format!("\"{}\" does not exist", path.display())),
+                    None => win.imp().navbar.show_error("Not a valid location"),
                 }
             }
         ));
 
-        Self::connect_list_navigation(&imp.content_panel.imp().file_list, self);
-        Self::connect_list_navigation(&imp.content_panel.imp().compact_list, self);
-        Self::connect_grid_navigation(&imp.content_panel.imp().flow_box, self);
-    }
-
-    fn connect_list_navigation(list_box: &gtk::ListBox, win: &OwlWindow) {
-        list_box.connect_row_activated(glib::clone!(
-            #[weak]
-            win,
-            move |_, row| {
-                let entries = win.imp().content_panel.imp().entries.borrow();
-                if let Some(entry) = entries.get(row.index() as usize) {
-                    if entry.is_dir {
-                        let path = entry.path.clone();
-                        drop(entries);
-                        win.navigate_to(path, true);
-                    }
+        imp.navbar.connect_query_changed(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |text| {
+                let imp = win.imp();
+                if imp.navbar.is_search_mode() && *imp.search_text.borrow() != text {
+                    win.start_search();
                 }
             }
         ));
-    }
+        imp.navbar.connect_stop_search(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move || win.stop_search()
+        ));
 
-    fn connect_grid_navigation(flow_box: &gtk::FlowBox, win: &OwlWindow) {
-        flow_box.connect_child_activated(glib::clone!(
-            #[weak]
-            win,
-            move |_, child| {
-                let entries = win.imp().content_panel.imp().entries.borrow();
-                if let Some(entry) = entries.get(child.index() as usize) {
-                    if entry.is_dir {
-                        let path = entry.path.clone();
-                        drop(entries);
-                        win.navigate_to(path, true);
-                    }
+        // Split view: each pane has its own path bar and becomes active on focus
+        Self::connect_tab(&imp.other_panel);
+        for (entry, other) in [(&imp.main_path_entry, false), (&imp.other_path_entry, true)] {
+            entry.connect_activate(glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                move |entry| win.enter_pane_location(entry, other)
+            ));
+            entry.connect_changed(|entry| {
+                entry.remove_css_class("error");
+                entry.set_tooltip_text(None);
+            });
+        }
+        for (pane, other) in [(&imp.main_pane, false), (&imp.other_pane, true)] {
+            let focus = gtk::EventControllerFocus::new();
+            focus.connect_enter(glib::clone!(
+                #[weak(rename_to = win)]
+                self,
+                move |_| win.set_other_active(other)
+            ));
+            pane.add_controller(focus);
+        }
+
+        imp.tabs.connect_switch_page(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |_, _, _| {
+                // Runs before the switch, so this ends the search in the tab being left
+                if win.imp().navbar.is_search_mode() {
+                    win.stop_search();
                 }
             }
         ));
+        imp.tabs.connect_page_notify(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |_| win.active_panel_changed()
+        ));
+        imp.tabs.connect_page_added(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |tabs, child, _| {
+                tabs.set_tab_reorderable(child, true);
+                tabs.set_tab_detachable(child, true);
+                win.tabs_changed();
+            }
+        ));
+        imp.tabs.connect_page_removed(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            move |_, _, _| win.tabs_changed()
+        ));
+        // Dropping a tab outside any window opens it in a new one
+        imp.tabs.connect_create_window(glib::clone!(
+            #[weak(rename_to = win)]
+            self,
+            #[upgrade_or]
+            None,
+            move |_, _| {
+                let app = win.application().and_downcast::<OwlApplication>()?;
+                let new_win = OwlWindow::empty(&app);
+                new_win.present();
+                Some(new_win.imp().tabs.get())
+            }
+        ));
     }
 
     pub fn setup_actions(&self) {
         self.add_action_entries([
             gio::ActionEntry::builder("go-back")
-                .activate(|win: &OwlWindow, _, _| {
-                    let imp = win.imp();
-                    let prev = { imp.history.borrow_mut().pop() };
-                    if let Some(path) = prev {
-                        let current = { imp.current_path.borrow().clone() };
-                        {
-                            imp.forward_stack.borrow_mut().push(current);
-                        }
-                        win.navigate_to(path, false);
+                .activate(|win: &OwlWindow, _, _| win.go_back(1))
+                .build(),
+            gio::ActionEntry::builder("go-forward")
+                .activate(|win: &OwlWindow, _, _| win.go_forward(1))
+                .build(),
+            // Chosen from the history menus of the navbar buttons
+            gio::ActionEntry::builder("go-back-steps")
+                .parameter_type(Some(glib::VariantTy::UINT32))
+                .activate(|win: &OwlWindow, _, param| {
+                    if let Some(steps) = param.and_then(|p| p.get::<u32>()) {
+                        win.go_back(steps as usize);
                     }
                 })
                 .build(),
-            gio::ActionEntry::builder("go-forward")
-                .activate(|win: &OwlWindow, _, _| {
-                    let imp = win.imp();
-                    let next = { imp.forward_stack.borrow_mut().pop() };
-                    if let Some(path) = next {
-                        let current = { imp.current_path.borrow().clone() };
-                        {
-                            imp.history.borrow_mut().push(current);
-                        }
-                        win.navigate_to(path, false);
+            gio::ActionEntry::builder("go-forward-steps")
+                .parameter_type(Some(glib::VariantTy::UINT32))
+                .activate(|win: &OwlWindow, _, param| {
+                    if let Some(steps) = param.and_then(|p| p.get::<u32>()) {
+                        win.go_forward(steps as usize);
                     }
                 })
                 .build(),
             gio::ActionEntry::builder("go-parent")
                 .activate(|win: &OwlWindow, _, _| {
-                    let current = win.imp().current_path.borrow().clone();
+                    let Some(current) = win.current_path() else {
+                        return;
+                    };
                     if let Some(parent) = current.parent() {
                         win.navigate_to(parent.to_path_buf(), true);
                     }
@@ -234,10 +1605,94 @@ impl OwlWindow {
                     win.navigate_to(Self::home_dir().join("Plantillas"), true);
                 })
                 .build(),
+            gio::ActionEntry::builder("command-palette")
+                .activate(|win: &OwlWindow, _, _| win.show_command_palette())
+                .build(),
+            gio::ActionEntry::builder("jump")
+                .activate(|win: &OwlWindow, _, _| win.show_jump_palette())
+                .build(),
+            gio::ActionEntry::builder("location")
+                .activate(|win: &OwlWindow, _, _| {
+                    if win.imp().navbar.is_search_mode() {
+                        win.stop_search();
+                    }
+                    win.imp().navbar.focus_location();
+                })
+                .build(),
             gio::ActionEntry::builder("refresh")
+                .activate(|win: &OwlWindow, _, _| win.reload())
+                .build(),
+            gio::ActionEntry::builder("search")
+                .state(false.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    let active = !action.state().unwrap().get::<bool>().unwrap();
+                    if active {
+                        win.set_search_mode(true);
+                        // Coming back to a previous query picks it up again
+                        if !win.imp().navbar.query().is_empty() {
+                            win.start_search();
+                        }
+                    } else {
+                        win.stop_search();
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("filter")
                 .activate(|win: &OwlWindow, _, _| {
-                    let current = win.imp().current_path.borrow().clone();
-                    win.navigate_to(current, false);
+                    if let Some(panel) = win.current_panel() {
+                        panel.toggle_filter();
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("search-mode")
+                .parameter_type(Some(glib::VariantTy::STRING))
+                .state(SearchMode::default().id().to_variant())
+                .activate(|win: &OwlWindow, action, param| {
+                    if let Some(s) = param.and_then(|p| p.get::<String>()) {
+                        action.set_state(&s.to_variant());
+                        if win.imp().navbar.is_search_mode() {
+                            win.start_search();
+                        }
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("save-search")
+                .activate(|win: &OwlWindow, _, _| win.prompt_save_search())
+                .build(),
+            gio::ActionEntry::builder("open-saved-search")
+                .parameter_type(Some(glib::VariantTy::STRING))
+                .activate(|win: &OwlWindow, _, param| {
+                    if let Some(name) = param.and_then(|p| p.get::<String>()) {
+                        win.open_saved_search(&name);
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("remove-saved-search")
+                .parameter_type(Some(glib::VariantTy::STRING))
+                .activate(|win: &OwlWindow, _, param| {
+                    let Some(name) = param.and_then(|p| p.get::<String>()) else {
+                        return;
+                    };
+                    let Some(app) = win.application().and_downcast::<OwlApplication>() else {
+                        return;
+                    };
+                    {
+                        let settings = app.settings();
+                        let mut s = settings.borrow_mut();
+                        s.remove_saved_search(&name);
+                    }
+                    app.save_settings_later();
+                    app.update_saved_searches();
+                })
+                .build(),
+            gio::ActionEntry::builder("search-case-sensitive")
+                .state(false.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    let new_state = !action.state().unwrap().get::<bool>().unwrap();
+                    action.set_state(&new_state.to_variant());
+                    if win.imp().navbar.is_search_mode() {
+                        win.start_search();
+                    }
                 })
                 .build(),
             gio::ActionEntry::builder("navigate")
@@ -260,12 +1715,130 @@ impl OwlWindow {
                 .build(),
             gio::ActionEntry::builder("open-terminal")
                 .activate(|win: &OwlWindow, _, _| {
-                    let current = win.imp().current_path.borrow().clone();
-                    let _ = std::process::Command::new("st")
-                        .current_dir(current)
-                        .spawn();
+                    let Some(app) = win.application().and_downcast::<OwlApplication>() else {
+                        return;
+                    };
+                    let Some(panel) = win.current_panel() else {
+                        return;
+                    };
+                    let template = app.settings().borrow().terminal.clone();
+                    // A selected folder takes precedence over the one being shown
+                    let dir = panel
+                        .selected_entry()
+                        .filter(|e| e.is_dir)
+                        .map(|e| e.path)
+                        .unwrap_or_else(|| panel.imp().path.borrow().clone());
+                    if let Err(message) = terminal::launch(&template, &dir) {
+                        win.show_toast(&message);
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("open-at-line")
+                .parameter_type(Some(glib::VariantTy::new("(su)").unwrap()))
+                .activate(|win: &OwlWindow, _, param| {
+                    let Some((path, line)) = param.and_then(|p| p.get::<(String, u32)>()) else {
+                        return;
+                    };
+                    let Some(app) = win.application().and_downcast::<OwlApplication>() else {
+                        return;
+                    };
+                    let template = app.settings().borrow().editor.clone();
+                    if let Err(message) =
+                        editor::open_at_line(&template, &PathBuf::from(path), line)
+                    {
+                        win.show_toast(&message);
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("preferences")
+                .activate(|win: &OwlWindow, _, _| {
+                    if let Some(app) = win.application().and_downcast::<OwlApplication>() {
+                        OwlPreferences::new(&app, win).present();
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("help-index")
+                .activate(|win: &OwlWindow, _, _| {
+                    if let Some(app) = win.application() {
+                        shortcuts::window(&app, win.upcast_ref()).present();
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("new-tab")
+                .activate(|win: &OwlWindow, _, _| {
+                    let path = win.current_path().unwrap_or_else(Self::home_dir);
+                    win.new_tab(path, true);
                 })
                 .build(),
+            gio::ActionEntry::builder("close-tab")
+                .activate(|win: &OwlWindow, _, _| {
+                    if let Some(panel) = win.tab_panel() {
+                        win.close_tab(&panel);
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("close-other-tabs")
+                .activate(|win: &OwlWindow, _, _| win.close_other_tabs())
+                .build(),
+            gio::ActionEntry::builder("next-tab")
+                .activate(|win: &OwlWindow, _, _| win.cycle_tabs(1))
+                .build(),
+            gio::ActionEntry::builder("previous-tab")
+                .activate(|win: &OwlWindow, _, _| win.cycle_tabs(-1))
+                .build(),
+            gio::ActionEntry::builder("split-view")
+                .state(false.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    let split = !action.state().unwrap().get::<bool>().unwrap();
+                    win.set_split(split);
+                })
+                .build(),
+            gio::ActionEntry::builder("copy")
+                .activate(|win: &OwlWindow, _, _| win.copy_files(false))
+                .build(),
+            gio::ActionEntry::builder("cut")
+                .activate(|win: &OwlWindow, _, _| win.copy_files(true))
+                .build(),
+            gio::ActionEntry::builder("paste")
+                .activate(|win: &OwlWindow, _, _| win.paste_files())
+                .build(),
+            gio::ActionEntry::builder("select-all")
+                .activate(|win: &OwlWindow, _, _| win.select_all())
+                .build(),
+            gio::ActionEntry::builder("rename")
+                .activate(|win: &OwlWindow, _, _| win.rename_selected())
+                .build(),
+            gio::ActionEntry::builder("trash")
+                .activate(|win: &OwlWindow, _, _| win.trash_selected())
+                .build(),
+            gio::ActionEntry::builder("delete")
+                .activate(|win: &OwlWindow, _, _| win.delete_selected())
+                .build(),
+            gio::ActionEntry::builder("copy-to-other-pane")
+                .activate(|win: &OwlWindow, _, _| win.transfer_to_other_pane(false))
+                .build(),
+            gio::ActionEntry::builder("move-to-other-pane")
+                .activate(|win: &OwlWindow, _, _| win.transfer_to_other_pane(true))
+                .build(),
+            // Files dropped on a breadcrumb: (sources, destination folder)
+            gio::ActionEntry::builder("move-into")
+                .parameter_type(Some(glib::VariantTy::new("(ass)").unwrap()))
+                .activate(|win: &OwlWindow, _, param| {
+                    let Some((sources, dest)) =
+                        param.and_then(|p| p.get::<(Vec<String>, String)>())
+                    else {
+                        return;
+                    };
+                    let sources = sources.into_iter().map(PathBuf::from).collect();
+                    win.transfer(sources, PathBuf::from(dest), true);
+                })
+                .build(),
+            gio::ActionEntry::builder("sync-panes")
+                .activate(|win: &OwlWindow, _, _| win.sync_panes())
+                .build(),
+            gio::ActionEntry::builder("swap-panes")
+                .activate(|win: &OwlWindow, _, _| win.swap_panes())
+                .build(),
             gio::ActionEntry::builder("close-window")
                 .activate(|win: &OwlWindow, _, _| win.close())
                 .build(),
@@ -275,13 +1848,9 @@ impl OwlWindow {
                 .activate(|win: &OwlWindow, action, param| {
                     if let Some(s) = param.and_then(|p| p.get::<String>()) {
                         action.set_state(&s.to_variant());
-                        let sort_by = match s.as_str() {
-                            "size" => SortBy::Size,
-                            "type" => SortBy::Type,
-                            "date" => SortBy::Date,
-                            _ => SortBy::Name,
-                        };
-                        win.imp().content_panel.set_sort_menu(sort_by);
+                        if let Some(panel) = win.current_panel() {
+                            panel.set_sort_menu(SortBy::from_id(&s));
+                        }
                     }
                 })
                 .build(),
@@ -295,10 +1864,36 @@ impl OwlWindow {
                             "descending" => SortOrder::Descending,
                             _ => SortOrder::Ascending,
                         };
-                        win.imp().content_panel.set_order(order);
+                        if let Some(panel) = win.current_panel() {
+                            panel.set_order(order);
+                        }
                     }
                 })
                 .build(),
+            gio::ActionEntry::builder("sort-natural")
+                .state(SortOptions::default().natural.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    win.toggle_sort_option(action, |options, on| options.natural = on)
+                })
+                .build(),
+            gio::ActionEntry::builder("sort-locale")
+                .state(SortOptions::default().locale.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    win.toggle_sort_option(action, |options, on| options.locale = on)
+                })
+                .build(),
+            gio::ActionEntry::builder("sort-ignore-punctuation")
+                .state(SortOptions::default().ignore_punctuation.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    win.toggle_sort_option(action, |options, on| options.ignore_punctuation = on)
+                })
+                .build(),
+            gio::ActionEntry::builder("folders-first")
+                .state(SortOptions::default().folders_first.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    win.toggle_sort_option(action, |options, on| options.folders_first = on)
+                })
+                .build(),
             gio::ActionEntry::builder("view")
                 .parameter_type(Some(glib::VariantTy::STRING))
                 .state("'list'".to_variant())
@@ -310,9 +1905,12 @@ impl OwlWindow {
                         let mode = match s.as_str() {
                             "'grid'" => ViewMode::Grid,
                             "'compact'" => ViewMode::Compact,
+                            "'columns'" => ViewMode::Columns,
                             _ => ViewMode::List,
                         };
-                        win.imp().content_panel.set_view_mode(mode);
+                        if let Some(panel) = win.current_panel() {
+                            panel.set_view_mode(mode);
+                        }
                     }
                 })
                 .build(),
@@ -321,24 +1919,47 @@ impl OwlWindow {
                 .activate(|win: &OwlWindow, action, _| {
                     let new_state = !action.state().unwrap().get::<bool>().unwrap();
                     action.set_state(&new_state.to_variant());
-                    *win.imp().content_panel.imp().show_hidden_files.borrow_mut() = new_state;
-                    let current = win.imp().current_path.borrow().clone();
-                    win.imp().content_panel.load_directory(&current);
+                    if let Some(panel) = win.current_panel() {
+                        panel.set_show_hidden_files(new_state);
+                        win.reload();
+                    }
                 })
                 .build(),
-            gio::ActionEntry::builder("sort")
+            gio::ActionEntry::builder("preview")
+                .state(false.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    let new_state = !action.state().unwrap().get::<bool>().unwrap();
+                    win.set_preview(new_state);
+                })
+                .build(),
+            gio::ActionEntry::builder("quick-look")
+                .activate(|win: &OwlWindow, _, _| win.show_quick_look())
+                .build(),
+            gio::ActionEntry::builder("tree-view")
+                .state(false.to_variant())
+                .activate(|win: &OwlWindow, action, _| {
+                    let new_state = !action.state().unwrap().get::<bool>().unwrap();
+                    action.set_state(&new_state.to_variant());
+                    if let Some(panel) = win.current_panel() {
+                        panel.set_tree_view(new_state);
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("set-default-view")
+                .activate(|win: &OwlWindow, _, _| {
+                    if let Some(panel) = win.current_panel() {
+                        panel.set_view_as_default();
+                        win.show_toast("New folders will open with this view");
+                    }
+                })
+                .build(),
+            gio::ActionEntry::builder("sort-column")
                 .parameter_type(Some(glib::VariantTy::STRING))
-                .state("name".to_variant())
-                .activate(|win: &OwlWindow, action, param| {
+                .activate(|win: &OwlWindow, _, param| {
                     if let Some(s) = param.and_then(|p| p.get::<String>()) {
-                        action.set_state(&s.to_variant());
-                        let sort_by = match s.as_str() {
-                            "size1" => SortBy::Size,
-                            "type1" => SortBy::Type,
-                            "date1" => SortBy::Date,
-                            _ => SortBy::Name,
-                        };
-                        win.imp().content_panel.set_sort(sort_by);
+                        if let Some(panel) = win.current_panel() {
+                            panel.set_sort(SortBy::from_id(&s));
+                        }
                     }
                 })
                 .build(),